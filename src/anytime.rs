@@ -0,0 +1,158 @@
+use std::collections::BinaryHeap;
+use std::collections::HashMap;
+use std::mem;
+use std::time::Instant;
+
+use crate::heuristic::Heuristic;
+use crate::node::{Node, TRAIL_BYTES};
+use crate::state::State;
+use crate::{moves, Limit, Limits};
+
+// Anytime weighted A*: a weighted search that carries on after its first solution, pruning
+// anything that can't beat the best one so far and reopening boards reached more cheaply,
+// until nothing better is left or a limit runs out
+pub struct Anytime<H: Heuristic> {
+    goal: Vec<Vec<usize>>,
+    target: State,
+    open: BinaryHeap<Node>,
+    // Fewest moves each board has been reached in
+    reached: HashMap<State, i64>,
+    heur: H,
+    weight: f64,
+    incumbent: Option<Node>,
+    max_space: usize,
+    expanded: usize,
+    limits: Limits,
+    started: Instant,
+    stopped: Option<Limit>,
+    best: Node,
+}
+
+impl<H: Heuristic> Anytime<H> {
+    pub fn new(
+        board: Vec<Vec<usize>>,
+        heur: H,
+        weight: f64,
+        goal: Vec<Vec<usize>>,
+        limits: Limits,
+    ) -> Anytime<H> {
+        let start = Node::new(board, &heur, weight, &goal);
+        let mut reached = HashMap::new();
+        reached.insert(start.state().clone(), 0);
+        let mut open = BinaryHeap::new();
+        open.push(start.clone());
+        Anytime {
+            target: State::pack(&goal),
+            goal,
+            open,
+            reached,
+            heur,
+            weight,
+            incumbent: None,
+            max_space: 1,
+            expanded: 0,
+            limits,
+            started: Instant::now(),
+            stopped: None,
+            best: start,
+        }
+    }
+
+    // Searches on until a shorter solution turns up, or None once there are no more
+    pub fn improve(&mut self) -> Option<Node> {
+        while let Some(node) = self.open.pop() {
+            self.stopped = self.limits.check(self.expanded, self.memory(), self.started);
+            if self.stopped.is_some() {
+                return None;
+            }
+            // Superseded by a cheaper way to the same board
+            if self.reached.get(node.state()).is_some_and(|&g| g < node.depth()) {
+                continue;
+            }
+            if self.pruned(&node) {
+                continue;
+            }
+            if node.reached(&self.target) {
+                self.incumbent = Some(node.clone());
+                return Some(node);
+            }
+            if node.dist() < self.best.dist() {
+                self.best = node.clone();
+            }
+            self.expanded += 1;
+            let (rows, cols) = (self.goal.len(), self.goal[0].len());
+            for (dir, _) in moves(node.blank(), rows, cols) {
+                let child = node.shift(dir, &self.heur, self.weight, &self.goal);
+                if self.pruned(&child) {
+                    continue;
+                }
+                if self.reached.get(child.state()).is_some_and(|&g| g <= child.depth()) {
+                    continue;
+                }
+                self.reached.insert(child.state().clone(), child.depth());
+                self.open.push(child);
+            }
+            if self.open.len() > self.max_space {
+                self.max_space = self.open.len();
+            }
+        }
+        None
+    }
+
+    fn memory(&self) -> usize {
+        let (rows, cols) = (self.goal.len(), self.goal[0].len());
+        let cells = State::heap_bytes(rows, cols);
+        let state = mem::size_of::<State>() + cells;
+        self.open.len() * (mem::size_of::<Node>() + cells + TRAIL_BYTES)
+            + self.reached.len() * (state + mem::size_of::<i64>())
+    }
+
+    // Whether node can't lead to anything shorter than the best solution so far
+    fn pruned(&self, node: &Node) -> bool {
+        let incumbent = match self.incumbent {
+            Some(ref incumbent) => incumbent.depth(),
+            None => return false,
+        };
+        if self.heur.admissible() {
+            node.cost() >= incumbent
+        } else {
+            // Without a trustworthy h, all that's sure is one more move unless it's the goal
+            node.depth() + i64::from(!node.reached(&self.target)) >= incumbent
+        }
+    }
+
+    // How many times longer than optimal the best solution so far can be. Nothing still open
+    // leads anywhere shorter than the lowest g + h left, so that's a floor on the optimum
+    pub fn bound(&self) -> Option<f64> {
+        let incumbent = self.incumbent.as_ref()?.depth();
+        if !self.heur.admissible() {
+            return None;
+        }
+        let floor = self.open.iter().map(|node| node.cost()).min().unwrap_or(incumbent);
+        let floor = floor.min(incumbent);
+        if floor == 0 {
+            return Some(1.0);
+        }
+        Some(incumbent as f64 / floor as f64)
+    }
+
+    pub fn incumbent(&self) -> Option<Node> {
+        self.incumbent.clone()
+    }
+
+    pub fn stopped(&self) -> Option<Limit> {
+        self.stopped
+    }
+
+    pub fn best(&self) -> Node {
+        self.best.clone()
+    }
+
+    pub fn space(&self) -> usize {
+        self.max_space
+    }
+
+    pub fn time(&self) -> usize {
+        self.expanded
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::BinaryHeap;
+use std::collections::{HashMap, HashSet};
+use std::mem;
+use std::time::Instant;
+
+use crate::heuristic::Heuristic;
+use crate::node::{Node, TRAIL_BYTES};
+use crate::state::State;
+use crate::{moves, Limit, Limits};
+
+// One half of the search, working from its own start towards its own goal
+struct Side<H: Heuristic> {
+    goal: Vec<Vec<usize>>,
+    heur: H,
+    open: BinaryHeap<Node>,
+    // Cheapest node found for each board, for checking where the two halves meet
+    seen: HashMap<State, Node>,
+    closed: HashSet<State>,
+}
+
+impl<H: Heuristic> Side<H> {
+    fn new(board: Vec<Vec<usize>>, heur: H, goal: Vec<Vec<usize>>) -> Side<H> {
+        let start = Node::new(board, &heur, 1.0, &goal);
+        let mut seen = HashMap::new();
+        seen.insert(start.state().clone(), start.clone());
+        let mut open = BinaryHeap::new();
+        open.push(start);
+        Side {
+            goal,
+            heur,
+            open,
+            seen,
+            closed: HashSet::new(),
+        }
+    }
+
+    // Lowest f left open, which no path through an open node can beat
+    fn floor(&self) -> i64 {
+        self.open.peek().map_or(i64::MAX, |node| node.cost())
+    }
+
+    // Expands the next open node, returning the children worth keeping
+    fn step(&mut self) -> Vec<Node> {
+        let node = match self.open.pop() {
+            Some(node) => node,
+            None => return Vec::new(),
+        };
+        if !self.closed.insert(node.state().clone()) {
+            return Vec::new();
+        }
+        let (rows, cols) = (self.goal.len(), self.goal[0].len());
+        let mut out = Vec::new();
+        for (dir, _) in moves(node.blank(), rows, cols) {
+            let child = node.shift(dir, &self.heur, 1.0, &self.goal);
+            if self.closed.contains(child.state()) {
+                continue;
+            }
+            if let Some(seen) = self.seen.get(child.state()) {
+                if seen.depth() <= child.depth() {
+                    continue;
+                }
+            }
+            self.seen.insert(child.state().clone(), child.clone());
+            self.open.push(child.clone());
+            out.push(child);
+        }
+        out
+    }
+
+    fn memory(&self) -> usize {
+        let (rows, cols) = (self.goal.len(), self.goal[0].len());
+        let cells = State::heap_bytes(rows, cols);
+        let node = mem::size_of::<Node>() + cells + TRAIL_BYTES;
+        self.open.len() * node + self.seen.len() * (node + mem::size_of::<State>() + cells)
+    }
+}
+
+// Front-to-end bidirectional A*: one search forwards from the board to the goal and one
+// backwards from the goal to the board, each with a heuristic pointed at its own target,
+// stopping once no path left open can beat the cheapest place they've met
+pub struct Bidir<F: Heuristic, B: Heuristic> {
+    forward: Side<F>,
+    backward: Side<B>,
+    // Cheapest meeting so far, as the forward and backward nodes for the same board
+    meeting: Option<(Node, Node)>,
+    max_space: usize,
+    limits: Limits,
+    started: Instant,
+    stopped: Option<Limit>,
+    best: Node,
+}
+
+impl<F: Heuristic, B: Heuristic> Bidir<F, B> {
+    pub fn new(
+        board: Vec<Vec<usize>>,
+        forward: F,
+        backward: B,
+        goal: Vec<Vec<usize>>,
+        limits: Limits,
+    ) -> Bidir<F, B> {
+        let forward = Side::new(board.clone(), forward, goal.clone());
+        let backward = Side::new(goal, backward, board);
+        let best = forward.open.peek().unwrap().clone();
+        let meeting = backward.seen.get(best.state()).map(|back| (best.clone(), back.clone()));
+        Bidir {
+            forward,
+            backward,
+            meeting,
+            max_space: 2,
+            limits,
+            started: Instant::now(),
+            stopped: None,
+            best,
+        }
+    }
+
+    // Blank positions from the board to the goal, as in Node::steps
+    pub fn solve(&mut self) -> Option<Vec<(usize, usize)>> {
+        loop {
+            let cost = self.meeting.as_ref().map_or(i64::MAX, |(f, b)| f.depth() + b.depth());
+            if cost <= self.forward.floor().max(self.backward.floor()) {
+                break;
+            }
+            if self.forward.open.is_empty() || self.backward.open.is_empty() {
+                break;
+            }
+            let memory = self.forward.memory() + self.backward.memory();
+            let expanded = self.time();
+            self.stopped = self.limits.check(expanded, memory, self.started);
+            if self.stopped.is_some() {
+                return None;
+            }
+            // Grow whichever frontier is smaller
+            if self.forward.open.len() <= self.backward.open.len() {
+                for child in self.forward.step() {
+                    if child.dist() < self.best.dist() {
+                        self.best = child.clone();
+                    }
+                    if let Some(back) = self.backward.seen.get(child.state()) {
+                        let back = back.clone();
+                        self.meet(child, back);
+                    }
+                }
+            } else {
+                for child in self.backward.step() {
+                    if let Some(front) = self.forward.seen.get(child.state()) {
+                        let front = front.clone();
+                        self.meet(front, child);
+                    }
+                }
+            }
+            let space = self.forward.open.len() + self.backward.open.len();
+            if space > self.max_space {
+                self.max_space = space;
+            }
+        }
+        let (front, back) = self.meeting.as_ref()?;
+        let mut out = front.steps();
+        out.extend(back.steps().into_iter().rev().skip(1));
+        Some(out)
+    }
+
+    fn meet(&mut self, front: Node, back: Node) {
+        let cost = front.depth() + back.depth();
+        match self.meeting {
+            Some((ref f, ref b)) if f.depth() + b.depth() <= cost => {}
+            _ => self.meeting = Some((front, back)),
+        }
+    }
+
+    pub fn optimal(&self) -> bool {
+        self.forward.heur.admissible() && self.backward.heur.admissible()
+    }
+
+    pub fn stopped(&self) -> Option<Limit> {
+        self.stopped
+    }
+
+    // Closest to the goal of the forward search's nodes
+    pub fn best(&self) -> Node {
+        self.best.clone()
+    }
+
+    pub fn space(&self) -> usize {
+        self.max_space
+    }
+
+    pub fn time(&self) -> usize {
+        self.forward.closed.len() + self.backward.closed.len()
+    }
+}
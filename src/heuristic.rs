@@ -0,0 +1,339 @@
+use crate::state::State;
+
+pub trait Heuristic {
+    /// Estimated number of moves left to turn board into goal.
+    fn estimate(&self, board: &[Vec<usize>], goal: &[Vec<usize>]) -> i64;
+
+    /// Estimate after tile slid from one cell to another, given the estimate h from before
+    /// the move and the packed board after it. Heuristics that can't do better than starting
+    /// over return None and get a full estimate instead.
+    fn update(
+        &self,
+        _h: i64,
+        _state: &State,
+        _goal: &[Vec<usize>],
+        _tile: usize,
+        _from: (usize, usize),
+        _to: (usize, usize),
+    ) -> Option<i64> {
+        None
+    }
+
+    /// Whether the estimate never exceeds the real distance, so A* finds optimal solutions.
+    fn admissible(&self) -> bool;
+}
+
+impl<H: Heuristic + ?Sized> Heuristic for Box<H> {
+    fn estimate(&self, board: &[Vec<usize>], goal: &[Vec<usize>]) -> i64 {
+        (**self).estimate(board, goal)
+    }
+
+    fn update(
+        &self,
+        h: i64,
+        state: &State,
+        goal: &[Vec<usize>],
+        tile: usize,
+        from: (usize, usize),
+        to: (usize, usize),
+    ) -> Option<i64> {
+        (**self).update(h, state, goal, tile, from, to)
+    }
+
+    fn admissible(&self) -> bool {
+        (**self).admissible()
+    }
+}
+
+impl<H: Heuristic + ?Sized> Heuristic for &H {
+    fn estimate(&self, board: &[Vec<usize>], goal: &[Vec<usize>]) -> i64 {
+        (**self).estimate(board, goal)
+    }
+
+    fn update(
+        &self,
+        h: i64,
+        state: &State,
+        goal: &[Vec<usize>],
+        tile: usize,
+        from: (usize, usize),
+        to: (usize, usize),
+    ) -> Option<i64> {
+        (**self).update(h, state, goal, tile, from, to)
+    }
+
+    fn admissible(&self) -> bool {
+        (**self).admissible()
+    }
+}
+
+pub struct Hamming;
+pub struct Nilsson;
+pub struct Custom;
+
+pub struct Manhattan {
+    homes: Vec<(usize, usize)>,
+}
+
+pub struct OutOfLine {
+    homes: Vec<(usize, usize)>,
+}
+
+pub struct LinearConflict {
+    homes: Vec<(usize, usize)>,
+}
+
+// Where each tile sits on the board
+fn positions(board: &[Vec<usize>]) -> Vec<(usize, usize)> {
+    let (rows, cols) = (board.len(), board[0].len());
+    let mut out = vec![(0, 0); rows * cols];
+    for i in 0..rows {
+        for j in 0..cols {
+            out[board[i][j]] = (i, j);
+        }
+    }
+    out
+}
+
+fn taxicab(a: (usize, usize), b: (usize, usize)) -> i64 {
+    (a.0 as i64 - b.0 as i64).abs() + (a.1 as i64 - b.1 as i64).abs()
+}
+
+fn manhattan(board: &[Vec<usize>], homes: &[(usize, usize)]) -> i64 {
+    let bdp = positions(board);
+    let mut h = 0;
+    for i in 1..bdp.len() {
+        h += taxicab(bdp[i], homes[i]);
+    }
+    h
+}
+
+impl Heuristic for Hamming {
+    fn estimate(&self, board: &[Vec<usize>], goal: &[Vec<usize>]) -> i64 {
+        let mut h = 0;
+        for i in 0..goal.len() {
+            for j in 0..goal[i].len() {
+                if board[i][j] != 0 && board[i][j] != goal[i][j] {
+                    h += 1;
+                }
+            }
+        }
+        h
+    }
+
+    fn update(
+        &self,
+        h: i64,
+        _state: &State,
+        goal: &[Vec<usize>],
+        tile: usize,
+        from: (usize, usize),
+        to: (usize, usize),
+    ) -> Option<i64> {
+        let was = (goal[from.0][from.1] != tile) as i64;
+        let now = (goal[to.0][to.1] != tile) as i64;
+        Some(h - was + now)
+    }
+
+    fn admissible(&self) -> bool {
+        true
+    }
+}
+
+impl Manhattan {
+    pub fn new(goal: &[Vec<usize>]) -> Manhattan {
+        Manhattan { homes: positions(goal) }
+    }
+}
+
+impl Heuristic for Manhattan {
+    fn estimate(&self, board: &[Vec<usize>], _goal: &[Vec<usize>]) -> i64 {
+        manhattan(board, &self.homes)
+    }
+
+    fn update(
+        &self,
+        h: i64,
+        _state: &State,
+        _goal: &[Vec<usize>],
+        tile: usize,
+        from: (usize, usize),
+        to: (usize, usize),
+    ) -> Option<i64> {
+        let home = self.homes[tile];
+        Some(h - taxicab(from, home) + taxicab(to, home))
+    }
+
+    fn admissible(&self) -> bool {
+        true
+    }
+}
+
+impl OutOfLine {
+    pub fn new(goal: &[Vec<usize>]) -> OutOfLine {
+        OutOfLine { homes: positions(goal) }
+    }
+
+    fn misplaced(&self, tile: usize, at: (usize, usize)) -> i64 {
+        let home = self.homes[tile];
+        (home.0 != at.0) as i64 + (home.1 != at.1) as i64
+    }
+}
+
+impl Heuristic for OutOfLine {
+    fn estimate(&self, board: &[Vec<usize>], _goal: &[Vec<usize>]) -> i64 {
+        let bdp = positions(board);
+        let mut h = 0;
+        for (tile, &at) in bdp.iter().enumerate().skip(1) {
+            h += self.misplaced(tile, at);
+        }
+        h
+    }
+
+    fn update(
+        &self,
+        h: i64,
+        _state: &State,
+        _goal: &[Vec<usize>],
+        tile: usize,
+        from: (usize, usize),
+        to: (usize, usize),
+    ) -> Option<i64> {
+        Some(h - self.misplaced(tile, from) + self.misplaced(tile, to))
+    }
+
+    fn admissible(&self) -> bool {
+        true
+    }
+}
+
+// The sequence score follows the snail around a square board
+impl Heuristic for Nilsson {
+    fn estimate(&self, board: &[Vec<usize>], goal: &[Vec<usize>]) -> i64 {
+        let len = goal.len();
+        let n = len * len;
+        let mut h = manhattan(board, &positions(goal));
+        for i in 0..(len / 2) {
+            for j in i..(len - i - 1) {
+                if (board[i][j] != 0 && board[i][j] != n - 1) && board[i][j] + 1 != board[i][j + 1]
+                {
+                    h += 6;
+                }
+                if (board[j][len - i - 1] != 0 && board[j][len - i - 1] != n - 1)
+                    && board[j][len - i - 1] + 1 != board[j + 1][len - i - 1]
+                {
+                    h += 6;
+                }
+                if (board[len - i - 1][len - j - 1] != 0
+                    && board[len - i - 1][len - j - 1] != n - 1)
+                    && board[len - i - 1][len - j - 1] + 1 != board[len - i - 1][len - j - 2]
+                {
+                    h += 6;
+                }
+                if (board[len - j - 1][i] != 0 && board[len - j - 1][i] != n - 1)
+                    && board[len - j - 1][i] + 1 != board[len - j - 2][i]
+                    && i == (len - 1) / 2
+                {
+                    h += 6;
+                }
+            }
+        }
+        if board[len / 2][(len - 1) / 2] != 0 {
+            h += 3;
+        }
+        h
+    }
+
+    fn admissible(&self) -> bool {
+        false
+    }
+}
+
+impl Heuristic for Custom {
+    fn estimate(&self, board: &[Vec<usize>], goal: &[Vec<usize>]) -> i64 {
+        10 * manhattan(board, &positions(goal))
+    }
+
+    fn admissible(&self) -> bool {
+        false
+    }
+}
+
+impl LinearConflict {
+    pub fn new(goal: &[Vec<usize>]) -> LinearConflict {
+        LinearConflict { homes: positions(goal) }
+    }
+
+    // Tiles in their goal line but out of order relative to each other: every one
+    // outside the longest in-order subsequence has to leave the line and come back
+    fn conflicts(&self, tiles: &[usize], line: usize, row: bool) -> i64 {
+        let mut order = Vec::with_capacity(tiles.len());
+        for &tile in tiles.iter().filter(|&&tile| tile != 0) {
+            let (y, x) = self.homes[tile];
+            if row && y == line {
+                order.push(x);
+            } else if !row && x == line {
+                order.push(y);
+            }
+        }
+        2 * (order.len() - longest_increasing(&order)) as i64
+    }
+}
+
+impl Heuristic for LinearConflict {
+    fn estimate(&self, board: &[Vec<usize>], _goal: &[Vec<usize>]) -> i64 {
+        let mut h = manhattan(board, &self.homes);
+        for (line, row) in board.iter().enumerate() {
+            h += self.conflicts(row, line, true);
+        }
+        for line in 0..board[0].len() {
+            let col: Vec<usize> = board.iter().map(|row| row[line]).collect();
+            h += self.conflicts(&col, line, false);
+        }
+        h
+    }
+
+    // A tile keeps its order in the line it slides along, so only the two lines
+    // across the move (the one it left and the one it joined) need a recount
+    fn update(
+        &self,
+        h: i64,
+        state: &State,
+        _goal: &[Vec<usize>],
+        tile: usize,
+        from: (usize, usize),
+        to: (usize, usize),
+    ) -> Option<i64> {
+        let home = self.homes[tile];
+        let mut h = h - taxicab(from, home) + taxicab(to, home);
+        let across_rows = from.0 != to.0;
+        let (rows, cols) = state.shape();
+        for &(cell, before) in [(from, tile), (to, 0)].iter() {
+            let (mut line, at, index): (Vec<usize>, _, _) = if across_rows {
+                ((0..cols).map(|j| state.get((cell.0, j))).collect(), cell.1, cell.0)
+            } else {
+                ((0..rows).map(|i| state.get((i, cell.1))).collect(), cell.0, cell.1)
+            };
+            h += self.conflicts(&line, index, across_rows);
+            line[at] = before;
+            h -= self.conflicts(&line, index, across_rows);
+        }
+        Some(h)
+    }
+
+    fn admissible(&self) -> bool {
+        true
+    }
+}
+
+fn longest_increasing(seq: &[usize]) -> usize {
+    let mut best = vec![1; seq.len()];
+    for i in 0..seq.len() {
+        for j in 0..i {
+            if seq[j] < seq[i] && best[j] + 1 > best[i] {
+                best[i] = best[j] + 1;
+            }
+        }
+    }
+    best.into_iter().max().unwrap_or(0)
+}
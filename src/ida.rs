@@ -0,0 +1,109 @@
+use std::mem;
+use std::time::Instant;
+
+use crate::heuristic::Heuristic;
+use crate::node::{Node, TRAIL_BYTES};
+use crate::state::State;
+use crate::{moves, Limit, Limits};
+
+pub struct Ida<H: Heuristic> {
+    goal: Vec<Vec<usize>>,
+    target: State,
+    root: Node,
+    heur: H,
+    max_space: usize,
+    expanded: usize,
+    limits: Limits,
+    started: Instant,
+    stopped: Option<Limit>,
+    best: Node,
+}
+
+impl<H: Heuristic> Ida<H> {
+    pub fn new(board: Vec<Vec<usize>>, heur: H, goal: Vec<Vec<usize>>, limits: Limits) -> Ida<H> {
+        let root = Node::new(board, &heur, 1.0, &goal);
+        Ida {
+            target: State::pack(&goal),
+            goal,
+            best: root.clone(),
+            root,
+            heur,
+            max_space: 1,
+            expanded: 0,
+            limits,
+            started: Instant::now(),
+            stopped: None,
+        }
+    }
+
+    pub fn solve(&mut self) -> Option<Node> {
+        let root = self.root.clone();
+        let mut bound = root.cost();
+        loop {
+            match self.search(&root, bound, 1) {
+                Ok(found) => return Some(found),
+                Err(i64::MAX) => return None,
+                Err(next) => bound = next,
+            }
+        }
+    }
+
+    // Depth-first search below the bound; on failure, returns the smallest cost that exceeded it
+    fn search(&mut self, node: &Node, bound: i64, depth: usize) -> Result<Node, i64> {
+        let cost = node.cost();
+        if cost > bound {
+            return Err(cost);
+        }
+        if node.reached(&self.target) {
+            return Ok(node.clone());
+        }
+        if node.dist() < self.best.dist() {
+            self.best = node.clone();
+        }
+        // Only the current path is held, so that's all the memory there is to count
+        let memory = depth * (mem::size_of::<Node>() + TRAIL_BYTES);
+        self.stopped = self.limits.check(self.expanded, memory, self.started);
+        if self.stopped.is_some() {
+            return Err(i64::MAX);
+        }
+        self.expanded += 1;
+        if depth > self.max_space {
+            self.max_space = depth;
+        }
+        let mut next = i64::MAX;
+        let (rows, cols) = (self.goal.len(), self.goal[0].len());
+        for (dir, to) in moves(node.blank(), rows, cols) {
+            // Never undo the move that got us here
+            if node.previous() == Some(to) {
+                continue;
+            }
+            let child = node.shift(dir, &self.heur, 1.0, &self.goal);
+            match self.search(&child, bound, depth + 1) {
+                Ok(found) => return Ok(found),
+                Err(_) if self.stopped.is_some() => return Err(i64::MAX),
+                Err(cost) => next = std::cmp::min(next, cost),
+            }
+        }
+        Err(next)
+    }
+
+    pub fn optimal(&self) -> bool {
+        self.heur.admissible()
+    }
+
+    pub fn stopped(&self) -> Option<Limit> {
+        self.stopped
+    }
+
+    pub fn best(&self) -> Node {
+        self.best.clone()
+    }
+
+    pub fn space(&self) -> usize {
+        self.max_space
+    }
+
+    pub fn time(&self) -> usize {
+        self.expanded
+    }
+}
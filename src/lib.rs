@@ -0,0 +1,1315 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use rand::prng::XorShiftRng;
+use rand::{Rng, SeedableRng};
+
+pub(crate) mod anytime;
+pub(crate) mod bidir;
+#[cfg(feature = "gui")]
+#[allow(clippy::toplevel_ref_arg)]
+pub mod game;
+pub mod heuristic;
+pub(crate) mod ida;
+pub mod moves;
+#[allow(clippy::ptr_arg)]
+pub(crate) mod node;
+pub mod parse;
+pub mod pdb;
+pub(crate) mod quest;
+pub mod state;
+pub mod verify;
+#[cfg(feature = "gui")]
+#[allow(clippy::ptr_arg, clippy::toplevel_ref_arg)]
+pub mod viz;
+pub mod walking;
+
+use crate::anytime::Anytime;
+use crate::bidir::Bidir;
+use crate::heuristic::{Heuristic, LinearConflict, Manhattan};
+use crate::ida::Ida;
+use crate::node::Node;
+use crate::quest::Quest;
+use crate::state::{State, MAX_CELLS};
+
+pub use crate::parse::{parse_batch, parse_input, write_board, ParseError};
+
+/// A board as rows of tiles, with 0 for the blank.
+pub type Board = Vec<Vec<usize>>;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Algorithm {
+    AStar,
+    Ida,
+    /// Weighted A* that keeps going after its first solution, reporting each shorter one,
+    /// until none is left or a limit is reached. It needs a weight above 1 to improve on.
+    Anytime,
+    /// A* from both ends at once, meeting in the middle. Optimal when both heuristics are
+    /// admissible and consistent.
+    Bidirectional,
+}
+
+/// Hands over each shorter solution an anytime search finds.
+pub type Report<'a> = Box<dyn FnMut(&Solution) + 'a>;
+
+/// How a search runs. The default is plain A* with no limits.
+pub struct Options<'a> {
+    pub algorithm: Algorithm,
+    /// A* and anytime search order boards by g + weight * h: 1 is plain A*, more trades
+    /// length for speed and infinity is greedy search. The others ignore it.
+    pub weight: f64,
+    pub limits: Limits,
+    /// For bidirectional search, a heuristic built with the puzzle as its goal to guide the
+    /// backward half. Manhattan distance when None.
+    pub backward: Option<Box<dyn Heuristic + 'a>>,
+    /// For anytime search, called with each shorter solution as it's found.
+    pub report: Option<Report<'a>>,
+}
+
+impl Default for Options<'_> {
+    fn default() -> Self {
+        Options {
+            algorithm: Algorithm::AStar,
+            weight: 1.0,
+            limits: Limits::default(),
+            backward: None,
+            report: None,
+        }
+    }
+}
+
+/// Budgets a search gives up at, each unlimited when None.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Limits {
+    /// Nodes expanded.
+    pub max_nodes: Option<usize>,
+    pub timeout: Option<Duration>,
+    /// Rough bytes held by the search, see `Limit::Memory`.
+    pub max_memory: Option<usize>,
+}
+
+/// The budget a search ran out of.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Limit {
+    Nodes,
+    Time,
+    /// Estimated from the nodes and states held, not measured, so treat it as approximate.
+    Memory,
+}
+
+impl Limits {
+    // The limit that ended the search, if one did
+    pub(crate) fn check(&self, nodes: usize, memory: usize, started: Instant) -> Option<Limit> {
+        if self.max_nodes.is_some_and(|max| nodes >= max) {
+            return Some(Limit::Nodes);
+        }
+        if self.max_memory.is_some_and(|max| memory >= max) {
+            return Some(Limit::Memory);
+        }
+        if self.timeout.is_some_and(|max| started.elapsed() >= max) {
+            return Some(Limit::Time);
+        }
+        None
+    }
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Limit::Nodes => write!(f, "node"),
+            Limit::Time => write!(f, "time"),
+            Limit::Memory => write!(f, "memory"),
+        }
+    }
+}
+
+/// Why a board can't reach its goal: every move keeps the parity of the tiles out of order,
+/// counting the blank's row on boards with an even number of columns, and the two differ.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Parity {
+    /// Pairs of tiles out of order on the board and on the goal.
+    pub inversions: (usize, usize),
+    /// The blank's row on the board and on the goal, None where it doesn't count.
+    pub blank_rows: Option<(usize, usize)>,
+}
+
+impl fmt::Display for Parity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (board, goal) = self.inversions;
+        match self.blank_rows {
+            Some((board_row, goal_row)) => write!(
+                f,
+                "the board has {} inversions with the blank on row {} and the goal {} with it \
+                 on row {}, but an even width needs inversions plus row to match in parity",
+                board,
+                board_row + 1,
+                goal,
+                goal_row + 1
+            ),
+            None => write!(
+                f,
+                "the board has {} inversions and the goal {}, but an odd width needs them to \
+                 match in parity",
+                board, goal
+            ),
+        }
+    }
+}
+
+/// Why `search` turned a board and goal away without looking at them further.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum BadInput {
+    /// No rows, or rows of different lengths.
+    Ragged,
+    /// The board and the goal are different sizes, as rows and columns each.
+    Shape { board: (usize, usize), goal: (usize, usize) },
+    /// More cells than a state can hold.
+    TooBig { cells: usize },
+    /// Something other than each tile from 0 to one less than the number of cells, once.
+    Tiles,
+}
+
+impl fmt::Display for BadInput {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BadInput::Ragged => write!(f, "the board or the goal has rows of different lengths"),
+            BadInput::Shape { board, goal } => write!(
+                f,
+                "the board is {}x{} but the goal is {}x{}",
+                board.0, board.1, goal.0, goal.1
+            ),
+            BadInput::TooBig { cells } => {
+                write!(f, "{} cells is too many, boards go up to {}", cells, MAX_CELLS)
+            }
+            BadInput::Tiles => {
+                write!(f, "the board and the goal must each hold every tile exactly once")
+            }
+        }
+    }
+}
+
+/// How a search ended.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Outcome {
+    Solved(Solution),
+    /// The board or the goal is malformed, found without searching.
+    Invalid(BadInput),
+    /// The goal can't be reached from the board, found without searching.
+    Insoluble(Parity),
+    /// Every board reachable from the start was searched without finding the goal.
+    SearchExhausted,
+    /// A budget ran out first. best leads to the board the heuristic put closest to the goal.
+    LimitReached { limit: Limit, best: Solution },
+}
+
+#[derive(Copy, Clone)]
+pub(crate) enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/// A solved puzzle and what it took to find it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Solution {
+    /// Position of the blank after each move, starting from the initial board.
+    pub steps: Vec<(usize, usize)>,
+    /// Most nodes held in memory at once.
+    pub space: usize,
+    /// Nodes expanded.
+    pub time: usize,
+    /// Heuristic estimate at the final board.
+    pub dist: i64,
+    /// Whether no shorter solution exists.
+    pub optimal: bool,
+    /// At most how many times longer than optimal the solution is, where that's known.
+    pub bound: Option<f64>,
+}
+
+impl Solution {
+    fn new(out: Node, space: usize, time: usize, bound: Option<f64>) -> Solution {
+        Solution {
+            steps: out.steps(),
+            space,
+            time,
+            dist: out.dist(),
+            optimal: bound == Some(1.0),
+            bound,
+        }
+    }
+
+    /// Number of moves in the solution.
+    pub fn len(&self) -> usize {
+        self.steps.len() - 1
+    }
+
+    /// Whether the puzzle was already solved, with no moves to make.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The solution as a move string, see `moves::replay`.
+    pub fn moves(&self) -> String {
+        moves::to_moves(&self.steps)
+    }
+}
+
+pub fn construct_basic_goal(n: usize) -> Board {
+    snail_goal(n, n)
+}
+
+/// Tiles spiralling clockwise in from the top left corner, with the blank where the spiral ends.
+pub fn snail_goal(rows: usize, cols: usize) -> Board {
+    let mut goal = vec![vec![0; cols]; rows];
+    let (mut top, mut bottom, mut left, mut right) = (0, rows, 0, cols);
+    let mut cells = Vec::with_capacity(rows * cols);
+    while top < bottom && left < right {
+        cells.extend((left..right).map(|j| (top, j)));
+        cells.extend(((top + 1)..bottom).map(|i| (i, right - 1)));
+        if top + 1 < bottom {
+            cells.extend((left..(right - 1)).rev().map(|j| (bottom - 1, j)));
+        }
+        if left + 1 < right {
+            cells.extend(((top + 1)..(bottom - 1)).rev().map(|i| (i, left)));
+        }
+        top += 1;
+        bottom -= 1;
+        left += 1;
+        right -= 1;
+    }
+    for (tile, &(i, j)) in cells.iter().enumerate().take(rows * cols - 1) {
+        goal[i][j] = tile + 1;
+    }
+    goal
+}
+
+/// Tiles in reading order, with the blank in the bottom right corner.
+pub fn row_major_goal(rows: usize, cols: usize) -> Board {
+    let mut goal: Board =
+        (0..rows).map(|i| (0..cols).map(|j| i * cols + j + 1).collect()).collect();
+    goal[rows - 1][cols - 1] = 0;
+    goal
+}
+
+/// Tiles in reading order after the blank in the top left corner.
+pub fn blank_first_goal(rows: usize, cols: usize) -> Board {
+    (0..rows).map(|i| (0..cols).map(|j| i * cols + j).collect()).collect()
+}
+
+/// Solves puzzle towards goal with plain A* or IDA*, or returns None if there's no solution.
+pub fn solve<H: Heuristic>(
+    puzzle: Board,
+    goal: Board,
+    heur: H,
+    algorithm: Algorithm,
+) -> Option<Solution> {
+    match search(puzzle, goal, heur, Options { algorithm, ..Options::default() }) {
+        Outcome::Solved(solution) => Some(solution),
+        _ => None,
+    }
+}
+
+/// Searches from puzzle towards goal as options say, and why there's no solution if there
+/// isn't one.
+pub fn search<H: Heuristic>(puzzle: Board, goal: Board, heur: H, options: Options) -> Outcome {
+    if let Some(bad) = check_input(&puzzle, &goal) {
+        return Outcome::Invalid(bad);
+    }
+    if let Some(parity) = parity(&puzzle, &goal) {
+        return Outcome::Insoluble(parity);
+    }
+    let Options { algorithm, weight, limits, backward, report } = options;
+    match algorithm {
+        Algorithm::AStar => astar(puzzle, goal, heur, weight, limits),
+        Algorithm::Ida => ida(puzzle, goal, heur, limits),
+        Algorithm::Anytime => anytime(puzzle, goal, heur, weight, limits, report),
+        Algorithm::Bidirectional => {
+            let backward = backward.unwrap_or_else(|| Box::new(Manhattan::new(&puzzle)));
+            bidirectional(puzzle, goal, heur, backward, limits)
+        }
+    }
+}
+
+fn check_input(puzzle: &Board, goal: &Board) -> Option<BadInput> {
+    let shape = |board: &Board| match board.first() {
+        Some(row) if !row.is_empty() && board.iter().all(|other| other.len() == row.len()) => {
+            Some((board.len(), row.len()))
+        }
+        _ => None,
+    };
+    let (board, goal_shape) = match (shape(puzzle), shape(goal)) {
+        (Some(board), Some(goal)) => (board, goal),
+        _ => return Some(BadInput::Ragged),
+    };
+    if board != goal_shape {
+        return Some(BadInput::Shape { board, goal: goal_shape });
+    }
+    let cells = board.0 * board.1;
+    if cells > MAX_CELLS {
+        return Some(BadInput::TooBig { cells });
+    }
+    for board in &[puzzle, goal] {
+        let mut seen = vec![false; cells];
+        for &tile in board.iter().flatten() {
+            if tile >= cells || seen[tile] {
+                return Some(BadInput::Tiles);
+            }
+            seen[tile] = true;
+        }
+    }
+    None
+}
+
+fn astar<H: Heuristic>(
+    puzzle: Board,
+    goal: Board,
+    heur: H,
+    weight: f64,
+    limits: Limits,
+) -> Outcome {
+    let mut quest = Quest::new(puzzle, heur, weight, goal, limits);
+    while quest.continues() {
+        if let Some(out) = quest.step() {
+            let (space, time) = (quest.space(), quest.time());
+            return Outcome::Solved(Solution::new(out, space, time, quest.bound()));
+        }
+    }
+    match quest.stopped() {
+        Some(limit) => {
+            let best = Solution::new(quest.best(), quest.space(), quest.time(), None);
+            Outcome::LimitReached { limit, best }
+        }
+        None => Outcome::SearchExhausted,
+    }
+}
+
+fn ida<H: Heuristic>(puzzle: Board, goal: Board, heur: H, limits: Limits) -> Outcome {
+    let mut ida = Ida::new(puzzle, heur, goal, limits);
+    match (ida.solve(), ida.stopped()) {
+        (Some(out), _) => {
+            let bound = if ida.optimal() { Some(1.0) } else { None };
+            Outcome::Solved(Solution::new(out, ida.space(), ida.time(), bound))
+        }
+        (None, Some(limit)) => {
+            let best = Solution::new(ida.best(), ida.space(), ida.time(), None);
+            Outcome::LimitReached { limit, best }
+        }
+        (None, None) => Outcome::SearchExhausted,
+    }
+}
+
+fn anytime<H: Heuristic>(
+    puzzle: Board,
+    goal: Board,
+    heur: H,
+    weight: f64,
+    limits: Limits,
+    mut report: Option<Report>,
+) -> Outcome {
+    let mut anytime = Anytime::new(puzzle, heur, weight, goal, limits);
+    while let Some(out) = anytime.improve() {
+        if let Some(report) = report.as_mut() {
+            report(&Solution::new(out, anytime.space(), anytime.time(), anytime.bound()));
+        }
+    }
+    let (space, time) = (anytime.space(), anytime.time());
+    match (anytime.incumbent(), anytime.stopped()) {
+        (Some(out), _) => Outcome::Solved(Solution::new(out, space, time, anytime.bound())),
+        (None, Some(limit)) => {
+            let best = Solution::new(anytime.best(), space, time, None);
+            Outcome::LimitReached { limit, best }
+        }
+        (None, None) => Outcome::SearchExhausted,
+    }
+}
+
+fn bidirectional<F: Heuristic, B: Heuristic>(
+    puzzle: Board,
+    goal: Board,
+    forward: F,
+    backward: B,
+    limits: Limits,
+) -> Outcome {
+    let mut bidir = Bidir::new(puzzle, forward, backward, goal, limits);
+    match (bidir.solve(), bidir.stopped()) {
+        (Some(steps), _) => Outcome::Solved(Solution {
+            steps,
+            space: bidir.space(),
+            time: bidir.time(),
+            dist: 0,
+            optimal: bidir.optimal(),
+            bound: if bidir.optimal() { Some(1.0) } else { None },
+        }),
+        (None, Some(limit)) => {
+            let best = Solution::new(bidir.best(), bidir.space(), bidir.time(), None);
+            Outcome::LimitReached { limit, best }
+        }
+        (None, None) => Outcome::SearchExhausted,
+    }
+}
+
+pub fn insoluble(board: &Board, goal: Option<Board>) -> bool {
+    let goal = goal.unwrap_or_else(|| snail_goal(board.len(), board[0].len()));
+    parity(board, &goal).is_some()
+}
+
+/// Why board can't be solved towards goal, or None if it can.
+pub fn parity(board: &Board, goal: &Board) -> Option<Parity> {
+    let (rows, cols) = (board.len(), board[0].len());
+    let n = rows * cols;
+    let mut inv_board = 0;
+    let mut inv_goal = 0;
+    let mut zero_row_board = 0;
+    let mut zero_row_goal = 0;
+    let mut weights_board = vec![0; n];
+    let mut weights_goal = weights_board.clone();
+    for i in 0..rows {
+        for j in 0..cols {
+            inv_board += weights_board[board[i][j]];
+            inv_goal += weights_goal[goal[i][j]];
+            if board[i][j] == 0 {
+                zero_row_board = i;
+            }
+            if goal[i][j] == 0 {
+                zero_row_goal = i;
+            }
+            for weight in weights_board.iter_mut().take(board[i][j]).skip(1) {
+                *weight += 1;
+            }
+            for weight in weights_goal.iter_mut().take(goal[i][j]).skip(1) {
+                *weight += 1;
+            }
+        }
+    }
+    // Moving the blank up or down changes the inversions by one less than the width, so
+    // with an odd width they keep their parity whatever row the blank is on
+    let blank_rows = if cols % 2 == 0 { Some((zero_row_board, zero_row_goal)) } else { None };
+    let rows_apart = blank_rows.map_or(0, |(board, goal)| board + goal);
+    if (inv_board + inv_goal + rows_apart) % 2 == 0 {
+        None
+    } else {
+        Some(Parity { inversions: (inv_board, inv_goal), blank_rows })
+    }
+}
+
+/// Random numbers for the generators that come out the same for a seed on every platform.
+// XorShift is fixed by its definition, unlike StdRng
+pub fn seeded_rng(seed: u64) -> XorShiftRng {
+    XorShiftRng::seed_from_u64(seed)
+}
+
+/// A random board that can be solved towards goal.
+pub fn puzzle_gen<R: Rng>(goal: &Board, rng: &mut R) -> Board {
+    let (rows, cols) = (goal.len(), goal[0].len());
+    let mut arr = Vec::with_capacity(rows * cols);
+    for x in 0..(rows * cols) {
+        arr.push(x);
+    }
+    // Rng::shuffle draws usize, which differs between 32 and 64 bit targets
+    for i in (1..arr.len()).rev() {
+        arr.swap(i, rng.gen_range(0, i as u32 + 1) as usize);
+    }
+    let mut out = Vec::with_capacity(rows);
+    for _ in 0..rows {
+        let mut row = Vec::with_capacity(cols);
+        for _ in 0..cols {
+            row.push(arr.pop().unwrap());
+        }
+        out.push(row);
+    }
+    // Swapping two tiles flips the parity without moving the blank, so half the
+    // shuffles get fixed up rather than thrown away
+    if insoluble(&out, Some(goal.clone())) {
+        let mut tiles = (0..rows * cols)
+            .map(|c| (c / cols, c % cols))
+            .filter(|&(i, j)| out[i][j] != 0);
+        let (a, b) = (tiles.next().unwrap(), tiles.next().unwrap());
+        let tile = out[a.0][a.1];
+        out[a.0][a.1] = out[b.0][b.1];
+        out[b.0][b.1] = tile;
+    }
+    out
+}
+
+/// Boards up to this many cells are generated breadth first, which also tells when nothing
+/// is as far as asked. Bigger boards need an optimal search to confirm each one.
+pub const EXACT_CELLS: usize = 9;
+
+/// Nodes each IDA* run confirming a generated board's depth may expand before giving up.
+pub const DEPTH_CHECK_NODES: usize = 1_000_000;
+
+/// A board made by `puzzle_at_depth`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Generated {
+    /// The optimal solution is exactly the depth asked for.
+    Exact(Board),
+    /// Too deep to confirm within `DEPTH_CHECK_NODES`, so the depth asked for is only a lower
+    /// bound, from linear conflict.
+    AtLeast(Board),
+}
+
+impl Generated {
+    pub fn board(self) -> Board {
+        match self {
+            Generated::Exact(board) | Generated::AtLeast(board) => board,
+        }
+    }
+}
+
+/// A random board depth moves from the goal, or None if none turned up. Up to EXACT_CELLS
+/// cells that's exact. Bigger boards walk away from the goal and confirm the distance with
+/// IDA*, falling back to a board at least depth moves out when that runs out of budget.
+pub fn puzzle_at_depth<R: Rng>(goal: &Board, depth: usize, rng: &mut R) -> Option<Generated> {
+    let (rows, cols) = (goal.len(), goal[0].len());
+    let blank = (0..rows * cols)
+        .map(|c| (c / cols, c % cols))
+        .find(|&(i, j)| goal[i][j] == 0)
+        .unwrap();
+    if rows * cols <= EXACT_CELLS {
+        // Breadth first out from the goal, so each layer is one move further
+        let mut seen = HashSet::new();
+        seen.insert(State::pack(goal));
+        let mut layer = vec![(State::pack(goal), blank)];
+        for _ in 0..depth {
+            let mut next = Vec::new();
+            for (state, blank) in layer.iter() {
+                for to in neighbours(*blank, rows, cols) {
+                    let mut state = state.clone();
+                    state.slide(to, *blank);
+                    if seen.insert(state.clone()) {
+                        next.push((state, to));
+                    }
+                }
+            }
+            if next.is_empty() {
+                return None;
+            }
+            layer = next;
+        }
+        let pick = rng.gen_range(0, layer.len() as u32) as usize;
+        return Some(Generated::Exact(layer[pick].0.unpack()));
+    }
+    // Walk away from the goal, favouring pushing tiles away from home, until even linear
+    // conflict puts the board at least depth moves out
+    let heur = LinearConflict::new(goal);
+    let mut walk = vec![goal.clone()];
+    let (mut blank, mut last) = (blank, blank);
+    loop {
+        let board = walk.last().unwrap();
+        let h = heur.estimate(board, goal) as usize;
+        if h >= depth {
+            break;
+        }
+        if walk.len() > 100 * depth + 1000 {
+            return None;
+        }
+        let after = |to: (usize, usize)| {
+            let mut next = board.clone();
+            next[blank.0][blank.1] = next[to.0][to.1];
+            next[to.0][to.1] = 0;
+            next
+        };
+        let options: Vec<_> =
+            neighbours(blank, rows, cols).into_iter().filter(|&to| to != last).collect();
+        let further: Vec<_> = options
+            .iter()
+            .cloned()
+            .filter(|&to| heur.estimate(&after(to), goal) as usize > h)
+            .collect();
+        let pool = if further.is_empty() { options } else { further };
+        let to = pool[rng.gen_range(0, pool.len() as u32) as usize];
+        walk.push(after(to));
+        last = blank;
+        blank = to;
+    }
+    // Every move changes the optimal distance by exactly one, starting from 0 at the goal,
+    // so some board on the walk is exactly depth out. Nothing fewer than depth moves in can
+    // be, which leaves bisecting between there and the end. The far board is always at
+    // least depth out, so it's what's left when a check runs out of nodes
+    let limits = Limits { max_nodes: Some(DEPTH_CHECK_NODES), ..Limits::default() };
+    let optimal = |board: &Board| {
+        let mut ida = Ida::new(board.clone(), &heur, goal.clone(), limits);
+        ida.solve().map(|found| found.depth() as usize)
+    };
+    let (mut near, mut far) = (depth.saturating_sub(1), walk.len() - 1);
+    let mut reach = optimal(&walk[far]);
+    while reach != Some(depth) {
+        if reach.is_none() {
+            return Some(Generated::AtLeast(walk.swap_remove(far)));
+        }
+        let mid = (near + far) / 2;
+        match optimal(&walk[mid]) {
+            Some(dist) if dist < depth => near = mid,
+            dist => {
+                far = mid;
+                reach = dist;
+            }
+        }
+    }
+    Some(Generated::Exact(walk.swap_remove(far)))
+}
+
+impl Direction {
+    // Where the blank at (y, x) ends up moving this way, unless that's off the board
+    fn step(self, (y, x): (usize, usize), rows: usize, cols: usize) -> Option<(usize, usize)> {
+        match self {
+            Direction::Up if y > 0 => Some((y - 1, x)),
+            Direction::Down if y + 1 < rows => Some((y + 1, x)),
+            Direction::Left if x > 0 => Some((y, x - 1)),
+            Direction::Right if x + 1 < cols => Some((y, x + 1)),
+            _ => None,
+        }
+    }
+}
+
+// The moves open to the blank and where each one takes it, in the order the searches try them
+pub(crate) fn moves(
+    blank: (usize, usize),
+    rows: usize,
+    cols: usize,
+) -> impl Iterator<Item = (Direction, (usize, usize))> {
+    [Direction::Left, Direction::Right, Direction::Up, Direction::Down]
+        .iter()
+        .filter_map(move |&dir| dir.step(blank, rows, cols).map(|to| (dir, to)))
+}
+
+// Where the blank can go, in the order generated boards have always been walked in
+fn neighbours(blank: (usize, usize), rows: usize, cols: usize) -> Vec<(usize, usize)> {
+    [Direction::Up, Direction::Down, Direction::Left, Direction::Right]
+        .iter()
+        .filter_map(|dir| dir.step(blank, rows, cols))
+        .collect()
+}
+
+#[cfg(test)]
+#[allow(clippy::needless_range_loop)]
+mod tests {
+    use std::fs::File;
+    use std::io::prelude::*;
+
+    use super::*;
+    use crate::heuristic::*;
+    use crate::pdb::Pdb;
+    use crate::state::State;
+    use crate::walking::WalkingDistance;
+
+    #[test]
+    fn reject_empty() {
+        let mut f = File::open("puzzles/parsing/empty.txt").expect("could not open file");
+        let mut contents = String::new();
+        f.read_to_string(&mut contents)
+            .expect("could not read file");
+        assert_eq!(ParseError::Empty, parse_input(contents).expect_err("returned"));
+    }
+
+    #[test]
+    fn reject_oblong() {
+        let mut f = File::open("puzzles/parsing/oblong.txt").expect("could not open file");
+        let mut contents = String::new();
+        f.read_to_string(&mut contents)
+            .expect("could not read file");
+        match parse_input(contents).expect_err("returned") {
+            ParseError::Ragged { at, expected: 2, found: 4 } => {
+                assert_eq!((2, 5), (at.line, at.column))
+            }
+            e => panic!("{:?}", e),
+        }
+    }
+
+    #[test]
+    fn reject_short() {
+        let mut f = File::open("puzzles/parsing/short.txt").expect("could not open file");
+        let mut contents = String::new();
+        f.read_to_string(&mut contents)
+            .expect("could not read file");
+        match parse_input(contents).expect_err("returned") {
+            ParseError::Missing { at, expected: 3, found: 2 } => {
+                assert_eq!((2, 4), (at.line, at.column))
+            }
+            e => panic!("{:?}", e),
+        }
+    }
+
+    #[test]
+    fn reject_letters() {
+        let mut f = File::open("puzzles/parsing/letters.txt").expect("could not open file");
+        let mut contents = String::new();
+        f.read_to_string(&mut contents)
+            .expect("could not read file");
+        let e = parse_input(contents).expect_err("returned");
+        assert_eq!("line 2, column 3: `x` is not a number\n2 | 8 x 4\n  |   ^", e.to_string());
+    }
+
+    #[test]
+    fn accept_header() {
+        let mut f = File::open("puzzles/parsing/header.txt").expect("could not open file");
+        let mut contents = String::new();
+        f.read_to_string(&mut contents)
+            .expect("could not read file");
+        assert_eq!(construct_basic_goal(3), parse_input(contents).expect("Error"));
+    }
+
+    #[test]
+    fn reject_header_rows() {
+        let mut f = File::open("puzzles/parsing/header_rows.txt").expect("could not open file");
+        let mut contents = String::new();
+        f.read_to_string(&mut contents)
+            .expect("could not read file");
+        match parse_input(contents).expect_err("returned") {
+            ParseError::RowCount { at, expected: 3, found: 2 } => assert_eq!(1, at.line),
+            e => panic!("{:?}", e),
+        }
+    }
+
+    #[test]
+    fn batch() {
+        let mut f = File::open("puzzles/parsing/batch.txt").expect("could not open file");
+        let mut contents = String::new();
+        f.read_to_string(&mut contents)
+            .expect("could not read file");
+        let puzzles = parse_batch(&contents);
+        assert_eq!(3, puzzles.len());
+        assert_eq!(vec![vec![1, 2, 3], vec![8, 4, 0], vec![7, 6, 5]], puzzles[0].clone().unwrap());
+        match puzzles[1].clone().expect_err("returned") {
+            ParseError::NotANumber { at, .. } => assert_eq!((8, 3), (at.line, at.column)),
+            e => panic!("{:?}", e),
+        }
+        assert_eq!(3, puzzles[2].clone().expect("Error").len());
+    }
+
+    #[test]
+    fn write_round_trip() {
+        for board in [construct_basic_goal(4), snail_goal(3, 4), blank_first_goal(1, 3)] {
+            let mut out = Vec::new();
+            write_board(&mut out, &board).expect("Error");
+            let contents = String::from_utf8(out).expect("Error");
+            assert_eq!(board, parse_input(contents).expect("Error"));
+        }
+    }
+
+    #[test]
+    fn accept_rect() {
+        let mut f = File::open("puzzles/parsing/rect.txt").expect("could not open file");
+        let mut contents = String::new();
+        f.read_to_string(&mut contents)
+            .expect("could not read file");
+        let puzzle = parse_input(contents).expect("Error");
+        assert!(puzzle.len() == 3);
+        for row in puzzle.iter() {
+            assert!(row.len() == 4);
+        }
+    }
+
+    #[test]
+    fn reject_invalid() {
+        let mut f = File::open("puzzles/parsing/wrong_nums.txt").expect("could not open file");
+        let mut contents = String::new();
+        f.read_to_string(&mut contents)
+            .expect("could not read file");
+        match parse_input(contents).expect_err("returned") {
+            ParseError::OutOfRange { at, value: 16, cells: 16 } => assert_eq!(4, at.line),
+            e => panic!("{:?}", e),
+        }
+    }
+
+    #[test]
+    fn reject_dup() {
+        let mut f = File::open("puzzles/parsing/dups.txt").expect("could not open file");
+        let mut contents = String::new();
+        f.read_to_string(&mut contents)
+            .expect("could not read file");
+        match parse_input(contents).expect_err("returned") {
+            ParseError::Duplicate { at, value: 2, first } => {
+                assert_eq!((2, 3, 1, 5), (at.line, at.column, first.line, first.column))
+            }
+            e => panic!("{:?}", e),
+        }
+    }
+
+    #[test]
+    fn accept_valid() {
+        let mut f = File::open("puzzles/parsing/clean.txt").expect("could not open file");
+        let mut contents = String::new();
+        f.read_to_string(&mut contents)
+            .expect("could not read file");
+        let puzzle = parse_input(contents).expect("Error");
+        assert!(puzzle.len() == 3);
+        for i in 0..3 {
+            assert!(puzzle[i].len() == 3);
+        }
+    }
+
+    #[test]
+    fn accept_comments() {
+        let mut f = File::open("puzzles/parsing/comments.txt").expect("could not open file");
+        let mut contents = String::new();
+        f.read_to_string(&mut contents)
+            .expect("could not read file");
+        let puzzle = parse_input(contents).expect("Error");
+        assert!(puzzle.len() == 3);
+        for i in 0..3 {
+            assert!(puzzle[i].len() == 3);
+        }
+    }
+
+    #[test]
+    fn ida_matches_astar() {
+        let puzzle = vec![vec![8, 1, 3], vec![7, 2, 4], vec![0, 6, 5]];
+        let goal = construct_basic_goal(3);
+        let astar =
+            solve(puzzle.clone(), goal.clone(), Manhattan::new(&goal), Algorithm::AStar);
+        let ida = solve(puzzle, goal.clone(), Manhattan::new(&goal), Algorithm::Ida);
+        let (astar, ida) = (astar.expect("Error"), ida.expect("Error"));
+        assert_eq!(astar.len(), ida.len());
+        assert_eq!(astar.steps.last(), ida.steps.last());
+    }
+
+    #[test]
+    fn linear_conflict_is_optimal() {
+        let puzzle = vec![vec![8, 1, 3], vec![7, 2, 4], vec![0, 6, 5]];
+        let goal = construct_basic_goal(3);
+        let manhattan =
+            solve(puzzle.clone(), goal.clone(), Manhattan::new(&goal), Algorithm::AStar);
+        let linear =
+            solve(puzzle, goal.clone(), LinearConflict::new(&goal), Algorithm::AStar);
+        assert_eq!(manhattan.expect("Error").len(), linear.expect("Error").len());
+    }
+
+    #[test]
+    fn pattern_database_is_exact_with_one_group() {
+        let puzzle = vec![vec![8, 1, 3], vec![7, 2, 4], vec![0, 6, 5]];
+        let goal = construct_basic_goal(3);
+        let db = Pdb::build(&goal, "8").expect("Error");
+        let steps =
+            solve(puzzle.clone(), goal.clone(), Manhattan::new(&goal), Algorithm::AStar);
+        assert_eq!(steps.expect("Error").len() as i64, db.estimate(&puzzle, &goal));
+    }
+
+    #[test]
+    fn pattern_database_round_trip() {
+        let goal = construct_basic_goal(3);
+        let path = std::env::temp_dir()
+            .join("npuzzle-test")
+            .join(Pdb::file_name(&goal, "4-4"));
+        let db = Pdb::build(&goal, "4-4").expect("Error");
+        db.save(&path).expect("Error");
+        assert!(Pdb::load(&path, &goal, "5-3").is_err());
+        let db = Pdb::load(&path, &goal, "4-4").expect("Error");
+        let puzzle = vec![vec![8, 1, 3], vec![7, 2, 4], vec![0, 6, 5]];
+        let manhattan =
+            solve(puzzle.clone(), goal.clone(), Manhattan::new(&goal), Algorithm::AStar);
+        let pattern = solve(puzzle, goal, db, Algorithm::AStar);
+        assert_eq!(manhattan.expect("Error").len(), pattern.expect("Error").len());
+    }
+
+    #[test]
+    fn walking_distance_is_admissible() {
+        let goal = construct_basic_goal(3);
+        let wd = WalkingDistance::new(&goal);
+        assert_eq!(0, wd.estimate(&goal, &goal));
+        let puzzle = vec![vec![8, 1, 3], vec![7, 2, 4], vec![0, 6, 5]];
+        let steps =
+            solve(puzzle.clone(), goal.clone(), Manhattan::new(&goal), Algorithm::AStar);
+        let walking = solve(puzzle, goal, wd, Algorithm::AStar);
+        assert_eq!(steps.expect("Error").len(), walking.expect("Error").len());
+    }
+
+    #[test]
+    fn plug_in_heuristic() {
+        struct Zero;
+        impl Heuristic for Zero {
+            fn estimate(&self, board: &[Vec<usize>], goal: &[Vec<usize>]) -> i64 {
+                if board == goal {
+                    0
+                } else {
+                    1
+                }
+            }
+
+            fn admissible(&self) -> bool {
+                true
+            }
+        }
+        let puzzle = vec![vec![8, 1, 3], vec![7, 2, 4], vec![0, 6, 5]];
+        let goal = construct_basic_goal(3);
+        let steps =
+            solve(puzzle.clone(), goal.clone(), Manhattan::new(&goal), Algorithm::AStar);
+        let zero = solve(puzzle, goal, Zero, Algorithm::AStar);
+        assert_eq!(steps.expect("Error").len(), zero.expect("Error").len());
+    }
+
+    #[test]
+    fn pack_round_trip() {
+        for &n in [3, 4, 5].iter() {
+            let goal = construct_basic_goal(n);
+            let mut state = State::pack(&goal);
+            assert_eq!(goal, state.unpack());
+            let blank = (n / 2, (n - 1) / 2);
+            let tile = state.slide((blank.0 - 1, blank.1), blank);
+            assert_eq!(goal[blank.0 - 1][blank.1], tile);
+            assert_eq!(0, state.get((blank.0 - 1, blank.1)));
+        }
+    }
+
+    #[test]
+    fn pack_big_boards() {
+        for goal in &[construct_basic_goal(17), row_major_goal(256, 1), row_major_goal(1, 300)] {
+            let state = State::pack(goal);
+            assert_eq!((goal.len(), goal[0].len()), state.shape());
+            assert_eq!(*goal, state.unpack());
+        }
+        let side = 257;
+        let contents: Vec<String> = (0..side)
+            .map(|i| (0..side).map(|j| (i * side + j).to_string()).collect::<Vec<_>>().join(" "))
+            .collect();
+        let err = parse_input(contents.join("\n")).expect_err("returned");
+        assert_eq!(ParseError::TooBig { cells: side * side }, err);
+    }
+
+    #[test]
+    fn updates_match_estimates() {
+        fn walk<H: Heuristic>(heur: H, goal: &Board) {
+            let len = goal.len();
+            let mut state = State::pack(goal);
+            let mut blank = (len / 2, (len - 1) / 2);
+            let mut h = heur.estimate(goal, goal);
+            let mut rng = seeded_rng(len as u64);
+            for _ in 0..2000 {
+                let (y, x) = (blank.0 as i64, blank.1 as i64);
+                let (dy, dx) = *rng.choose(&[(-1, 0), (1, 0), (0, -1), (0, 1)]).unwrap();
+                if y + dy < 0 || y + dy >= len as i64 || x + dx < 0 || x + dx >= len as i64 {
+                    continue;
+                }
+                let from = ((y + dy) as usize, (x + dx) as usize);
+                let tile = state.slide(from, blank);
+                h = heur.update(h, &state, goal, tile, from, blank).expect("Error");
+                assert_eq!(heur.estimate(&state.unpack(), goal), h);
+                blank = from;
+            }
+        }
+        for &n in [3, 4, 5].iter() {
+            let goal = construct_basic_goal(n);
+            walk(Hamming, &goal);
+            walk(Manhattan::new(&goal), &goal);
+            walk(OutOfLine::new(&goal), &goal);
+            walk(LinearConflict::new(&goal), &goal);
+        }
+    }
+
+    #[test]
+    fn rectangular_goals() {
+        assert_eq!(vec![vec![1, 2, 3], vec![8, 0, 4], vec![7, 6, 5]], construct_basic_goal(3));
+        let snail = vec![vec![1, 2, 3, 4], vec![10, 11, 0, 5], vec![9, 8, 7, 6]];
+        assert_eq!(snail, snail_goal(3, 4));
+        let row_major = vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8, 9], vec![10, 11, 0]];
+        assert_eq!(row_major, row_major_goal(4, 3));
+        assert_eq!(vec![vec![0, 1, 2], vec![3, 4, 5]], blank_first_goal(2, 3));
+    }
+
+    #[test]
+    fn solve_rectangle() {
+        let puzzle = vec![vec![11, 10, 9, 8], vec![7, 6, 5, 4], vec![3, 2, 1, 0]];
+        for goal in [snail_goal(3, 4), row_major_goal(3, 4)] {
+            if insoluble(&puzzle, Some(goal.clone())) {
+                continue;
+            }
+            let linear = solve(
+                puzzle.clone(),
+                goal.clone(),
+                LinearConflict::new(&goal),
+                Algorithm::Ida,
+            );
+            let wd = WalkingDistance::new(&goal);
+            let walking = solve(puzzle.clone(), goal.clone(), wd, Algorithm::Ida);
+            assert_eq!(linear.expect("Error").len(), walking.expect("Error").len());
+        }
+        let goal = snail_goal(2, 3);
+        let puzzle = vec![vec![5, 1, 2], vec![0, 4, 3]];
+        let db = Pdb::build(&goal, "5").expect("Error");
+        let steps = solve(puzzle.clone(), goal.clone(), Hamming, Algorithm::AStar);
+        assert_eq!(steps.expect("Error").len() as i64, db.estimate(&puzzle, &goal));
+    }
+
+    #[test]
+    fn rectangular_solvability() {
+        for &(rows, cols) in [(2, 3), (3, 2), (2, 4)].iter() {
+            let goal = row_major_goal(rows, cols);
+            let mut seen = HashSet::new();
+            let mut queue = vec![goal.clone()];
+            seen.insert(goal.clone());
+            while let Some(board) = queue.pop() {
+                let blank = (0..rows * cols).find(|&c| board[c / cols][c % cols] == 0).unwrap();
+                let (y, x) = (blank / cols, blank % cols);
+                let (up, left) = (y.wrapping_sub(1), x.wrapping_sub(1));
+                let moves = [(up, x), (y + 1, x), (y, left), (y, x + 1)];
+                for &(i, j) in moves.iter().filter(|&&(i, j)| i < rows && j < cols) {
+                    let mut next = board.clone();
+                    next[y][x] = board[i][j];
+                    next[i][j] = 0;
+                    if seen.insert(next.clone()) {
+                        queue.push(next);
+                    }
+                }
+            }
+            for board in seen.iter() {
+                assert!(!insoluble(board, Some(goal.clone())));
+                // Swapping two tiles always lands in the other half of the permutations
+                let mut swapped = board.clone();
+                let tiles: Vec<_> = (0..rows * cols)
+                    .map(|c| (c / cols, c % cols))
+                    .filter(|&(i, j)| board[i][j] != 0)
+                    .take(2)
+                    .collect();
+                swapped[tiles[0].0][tiles[0].1] = board[tiles[1].0][tiles[1].1];
+                swapped[tiles[1].0][tiles[1].1] = board[tiles[0].0][tiles[0].1];
+                assert!(insoluble(&swapped, Some(goal.clone())));
+            }
+        }
+    }
+
+    #[test]
+    fn move_strings() {
+        let puzzle = vec![vec![8, 1, 3], vec![7, 2, 4], vec![0, 6, 5]];
+        let goal = construct_basic_goal(3);
+        let solution = solve(puzzle.clone(), goal.clone(), Hamming, Algorithm::AStar);
+        let moves = solution.expect("Error").moves();
+        assert_eq!(goal, moves::replay(&puzzle, &moves).expect("Error"));
+        let wrapped = format!("{}\n{}", &moves[..3], &moves[3..]);
+        assert_eq!(goal, moves::replay(&puzzle, &wrapped).expect("Error"));
+        match moves::replay(&puzzle, "LLL") {
+            Err(moves::MoveError::Illegal { step: 3, letter: 'L', blank }) => {
+                assert_eq!((2, 2), blank)
+            }
+            e => panic!("{:?}", e),
+        }
+        match moves::replay(&puzzle, "Lx") {
+            Err(moves::MoveError::Unknown { step: 2, letter: 'x' }) => {}
+            e => panic!("{:?}", e),
+        }
+    }
+
+    #[test]
+    fn verify_solutions() {
+        use crate::verify::{verify, VerifyError};
+        let puzzle = vec![vec![8, 1, 3], vec![7, 2, 4], vec![0, 6, 5]];
+        let goal = construct_basic_goal(3);
+        let solution = solve(puzzle.clone(), goal.clone(), Hamming, Algorithm::Ida);
+        let mut steps = solution.expect("Error").steps;
+        assert_eq!(Ok(()), verify(&puzzle, &goal, &steps));
+        assert_eq!(Err(VerifyError::Empty), verify(&puzzle, &goal, &[]));
+        let end = steps.pop().unwrap();
+        match verify(&puzzle, &goal, &steps) {
+            Err(VerifyError::WrongEnd { .. }) => {}
+            e => panic!("{:?}", e),
+        }
+        steps.push((end.0, end.1 + 1));
+        match verify(&puzzle, &goal, &steps) {
+            Err(VerifyError::Jump { step, .. }) => assert_eq!(steps.len() - 1, step),
+            e => panic!("{:?}", e),
+        }
+    }
+
+    #[test]
+    fn seeded_generation() {
+        let goal = construct_basic_goal(3);
+        let board = puzzle_gen(&goal, &mut seeded_rng(7));
+        // Pinned, so a change in what a seed means shows up here
+        assert_eq!(vec![vec![1, 2, 5], vec![8, 3, 6], vec![4, 0, 7]], board);
+        assert!(!insoluble(&board, Some(goal.clone())));
+        let boards: HashSet<Board> =
+            (0..20).map(|seed| puzzle_gen(&goal, &mut seeded_rng(seed))).collect();
+        assert!(boards.len() > 1);
+        // Odd shuffles get their parity fixed rather than rerolled
+        for goal in &[snail_goal(3, 4), row_major_goal(4, 4), blank_first_goal(2, 5)] {
+            for seed in 0..50 {
+                assert!(!insoluble(&puzzle_gen(goal, &mut seeded_rng(seed)), Some(goal.clone())));
+            }
+        }
+    }
+
+    #[test]
+    fn generate_at_depth() {
+        let goal = construct_basic_goal(3);
+        // With the blank in the middle nothing is more than 30 moves out
+        for &depth in &[0, 1, 14, 30] {
+            let board = match puzzle_at_depth(&goal, depth, &mut seeded_rng(depth as u64)) {
+                Some(Generated::Exact(board)) => board,
+                other => panic!("{:?}", other),
+            };
+            let heur = LinearConflict::new(&goal);
+            let solution = solve(board, goal.clone(), heur, Algorithm::AStar);
+            assert_eq!(depth, solution.expect("Error").len());
+        }
+        assert_eq!(None, puzzle_at_depth(&goal, 31, &mut seeded_rng(0)));
+        let goal = construct_basic_goal(4);
+        let board = match puzzle_at_depth(&goal, 40, &mut seeded_rng(0)) {
+            Some(Generated::Exact(board)) => board,
+            other => panic!("{:?}", other),
+        };
+        let solution = solve(board, goal.clone(), LinearConflict::new(&goal), Algorithm::Ida);
+        assert_eq!(40, solution.expect("Error").len());
+    }
+
+    #[test]
+    fn deep_boards_fall_back_to_a_bound() {
+        for &(len, depth) in &[(4, 60), (5, 80)] {
+            let goal = construct_basic_goal(len);
+            let started = Instant::now();
+            let board = match puzzle_at_depth(&goal, depth, &mut seeded_rng(0)) {
+                Some(Generated::AtLeast(board)) => board,
+                other => panic!("{:?}", other),
+            };
+            assert!(started.elapsed() < Duration::from_secs(30));
+            assert!(LinearConflict::new(&goal).estimate(&board, &goal) as usize >= depth);
+        }
+    }
+
+    #[test]
+    fn outcomes() {
+        let goal = construct_basic_goal(4);
+        let puzzle = puzzle_at_depth(&goal, 40, &mut seeded_rng(0)).unwrap().board();
+        let limits = Limits { max_nodes: Some(500), ..Limits::default() };
+        for &algorithm in &[Algorithm::AStar, Algorithm::Ida] {
+            let heur = Manhattan::new(&goal);
+            let options = Options { algorithm, limits, ..Options::default() };
+            match search(puzzle.clone(), goal.clone(), &heur, options) {
+                Outcome::LimitReached { limit: Limit::Nodes, best } => {
+                    assert_eq!(500, best.time);
+                    let board = moves::replay(&puzzle, &best.moves()).unwrap();
+                    assert_eq!(best.dist, heur.estimate(&board, &goal));
+                    assert!(best.dist < heur.estimate(&puzzle, &goal));
+                }
+                _ => panic!("expected the node limit"),
+            }
+        }
+        // Boards that can't be solved are turned away before searching
+        let goal = construct_basic_goal(2);
+        let puzzle = vec![vec![2, 1], vec![3, 0]];
+        let options = Options { algorithm: Algorithm::Ida, limits, ..Options::default() };
+        match search(puzzle, goal, Hamming, options) {
+            Outcome::Insoluble(parity) => {
+                assert_eq!(Parity { inversions: (1, 0), blank_rows: Some((1, 1)) }, parity)
+            }
+            _ => panic!("expected the board to be insoluble"),
+        }
+            // So are malformed ones, rather than panicking partway through
+        let goal = construct_basic_goal(3);
+        let malformed = [
+            (vec![vec![1, 2], vec![3, 0]], BadInput::Shape { board: (2, 2), goal: (3, 3) }),
+            (vec![vec![1, 2, 3], vec![4, 5, 6], vec![7, 8, 9]], BadInput::Tiles),
+            (vec![vec![1, 2, 3], vec![4, 0], vec![7, 8, 6]], BadInput::Ragged),
+            (Vec::new(), BadInput::Ragged),
+        ];
+        for (puzzle, bad) in malformed.iter().cloned() {
+            match search(puzzle, goal.clone(), Hamming, Options::default()) {
+                Outcome::Invalid(found) => assert_eq!(bad, found),
+                other => panic!("expected {:?}, got {:?}", bad, other),
+            }
+        }
+    }
+
+    #[test]
+    fn goal_reached_whatever_the_estimate() {
+        // Nilsson's sequence score follows the snail, so it's never 0 on this goal
+        let goal = row_major_goal(3, 3);
+        assert!(Nilsson.estimate(&goal, &goal) > 0);
+        let puzzle = puzzle_at_depth(&goal, 8, &mut seeded_rng(1)).unwrap().board();
+        use Algorithm::*;
+        for &algorithm in &[AStar, Ida, Anytime, Bidirectional] {
+            let options = Options {
+                algorithm,
+                weight: 2.0,
+                backward: Some(Box::new(Nilsson)),
+                ..Options::default()
+            };
+            match search(puzzle.clone(), goal.clone(), Nilsson, options) {
+                Outcome::Solved(solution) => {
+                    assert!(verify::verify(&puzzle, &goal, &solution.steps).is_ok())
+                }
+                _ => panic!("expected a solution"),
+            }
+        }
+    }
+
+    #[test]
+    fn bidirectional_matches_astar() {
+        let goal = construct_basic_goal(3);
+        let mut puzzles: Vec<Board> =
+            (0..10).map(|seed| puzzle_gen(&goal, &mut seeded_rng(seed))).collect();
+        let big = construct_basic_goal(4);
+        puzzles.push(puzzle_at_depth(&big, 30, &mut seeded_rng(3)).unwrap().board());
+        for puzzle in puzzles {
+            let goal = if puzzle.len() == 4 { big.clone() } else { goal.clone() };
+            let (forward, backward) = (LinearConflict::new(&goal), LinearConflict::new(&puzzle));
+            let options = Options {
+                algorithm: Algorithm::Bidirectional,
+                backward: Some(Box::new(backward)),
+                ..Options::default()
+            };
+            let both = match search(puzzle.clone(), goal.clone(), forward, options) {
+                Outcome::Solved(solution) => solution,
+                _ => panic!("expected a solution"),
+            };
+            let heur = Manhattan::new(&goal);
+            let one = solve(puzzle.clone(), goal.clone(), heur, Algorithm::AStar);
+            assert_eq!(one.expect("Error").len(), both.len());
+            assert!(both.optimal);
+            assert_eq!(Ok(()), verify::verify(&puzzle, &goal, &both.steps));
+        }
+    }
+
+    #[test]
+    fn weighted_within_bound() {
+        let goal = construct_basic_goal(3);
+        for seed in 0..10 {
+            let puzzle = puzzle_gen(&goal, &mut seeded_rng(seed));
+            let solve_at = |weight| {
+                let heur = Manhattan::new(&goal);
+                let options = Options { weight, ..Options::default() };
+                match search(puzzle.clone(), goal.clone(), heur, options) {
+                    Outcome::Solved(solution) => solution,
+                    _ => panic!("expected a solution"),
+                }
+            };
+            let optimal = solve_at(1.0);
+            assert_eq!(Some(1.0), optimal.bound);
+            for &weight in &[1.5, 3.0] {
+                let weighted = solve_at(weight);
+                assert_eq!(Some(weight), weighted.bound);
+                assert!(weighted.len() as f64 <= weight * optimal.len() as f64);
+            }
+            let greedy = solve_at(f64::INFINITY);
+            assert_eq!(None, greedy.bound);
+            assert!(!greedy.optimal);
+        }
+    }
+
+    #[test]
+    fn anytime_improves_to_optimal() {
+        let goal = construct_basic_goal(4);
+        let puzzle = puzzle_at_depth(&goal, 36, &mut seeded_rng(2)).unwrap().board();
+        let heur = LinearConflict::new(&goal);
+        let mut lengths = Vec::new();
+        let options = Options {
+            algorithm: Algorithm::Anytime,
+            weight: 3.0,
+            report: Some(Box::new(|s: &Solution| {
+                assert_eq!(Ok(()), verify::verify(&puzzle, &goal, &s.steps));
+                lengths.push(s.len());
+            })),
+            ..Options::default()
+        };
+        let outcome = search(puzzle.clone(), goal.clone(), &heur, options);
+        let solution = match outcome {
+            Outcome::Solved(solution) => solution,
+            _ => panic!("expected a solution"),
+        };
+        assert!(solution.optimal);
+        assert_eq!(Some(&solution.len()), lengths.last());
+        assert!(lengths.windows(2).all(|w| w[1] < w[0]));
+        let astar = solve(puzzle, goal.clone(), &heur, Algorithm::AStar);
+        assert_eq!(astar.expect("Error").len(), solution.len());
+    }
+}
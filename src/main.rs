@@ -1,204 +1,406 @@
-use std::collections::HashSet;
+use std::collections::HashMap;
 use std::fs::File;
-use std::io::prelude::*;
+use std::io::{self, prelude::*};
+use std::time::{Duration, Instant};
 
+use clap::{App, AppSettings, Arg, ArgMatches, Error, ErrorKind, SubCommand};
 use rand::{thread_rng, Rng};
 
-use clap::{App, Arg};
-
+#[cfg(feature = "gui")]
 use glutin_window::GlutinWindow as Window;
+#[cfg(feature = "gui")]
 use opengl_graphics::{GlGraphics, OpenGL};
+#[cfg(feature = "gui")]
 use piston::event_loop::*;
+#[cfg(feature = "gui")]
 use piston::input::*;
+#[cfg(feature = "gui")]
 use piston::window::WindowSettings;
 
-mod game;
-mod node;
-mod quest;
-mod viz;
-
-use crate::game::Game;
-use crate::quest::Quest;
-use crate::viz::Viz;
-
-#[derive(Copy, Clone, Hash, Eq, PartialEq)]
-pub enum Heuristic {
-    Hamming,
-    Manhattan,
-    OutOfLine,
-    Nilsson,
-    Custom,
-}
+#[cfg(feature = "gui")]
+use npuzzle::game::Game;
+use npuzzle::heuristic::*;
+use npuzzle::pdb::{self, Pdb};
+#[cfg(feature = "gui")]
+use npuzzle::viz::Viz;
+use npuzzle::moves::{self, MoveError};
+use npuzzle::verify::verify;
+use npuzzle::walking::{self, WalkingDistance};
+use npuzzle::{blank_first_goal, row_major_goal, snail_goal};
+use npuzzle::{parity, parse_batch, parse_input, write_board};
+use npuzzle::{puzzle_at_depth, puzzle_gen, seeded_rng, Generated, EXACT_CELLS};
+use npuzzle::{search, Algorithm, Board, Limits, Options, Outcome, Solution};
+
+#[cfg(feature = "gui")]
+const WIDTH: u32 = 500;
 
-#[derive(Copy, Clone)]
-pub(crate) enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
+fn partition<'a>(matches: &'a ArgMatches, rows: usize, cols: usize) -> &'a str {
+    match matches.value_of("partition").or(Pdb::default_partition(rows, cols)) {
+        Some(partition) => partition,
+        None => Error::with_description(
+            "no default pattern database partition for this size, use --partition",
+            ErrorKind::MissingRequiredArgument,
+        )
+        .exit(),
+    }
 }
 
-fn parse_input(contents: String) -> Result<Vec<Vec<usize>>, &'static str> {
-    let mut out = Vec::new();
-    let lines = contents.lines();
-    for line in lines {
-        let words = line.split_whitespace();
-        let mut tmp = Vec::new();
-        for word in words {
-            if word.chars().next().unwrap() == '#' {
-                break;
-            }
-            tmp.push(word.parse::<usize>().unwrap_or(0));
-        }
-        if !tmp.is_empty() {
-            out.push(tmp);
-        }
+fn open_pdb(matches: &ArgMatches, goal: &Board) -> Pdb {
+    let dir = pdb::cache_dir(matches.value_of("cache-dir"));
+    let partition = partition(matches, goal.len(), goal[0].len());
+    let path = dir.join(Pdb::file_name(goal, partition));
+    if !path.exists() {
+        // On stderr so json output stays one object per line
+        eprintln!("Generating pattern database {}...", path.display());
     }
-    if out.is_empty() {
-        return Err("no input");
+    match Pdb::open(&dir, goal, partition) {
+        Ok(db) => db,
+        Err(e) => Error::with_description(
+            &format!("could not open pattern database: {}", e),
+            ErrorKind::InvalidValue,
+        )
+        .exit(),
     }
-    let len = out[0].len();
-    let mut height = 0;
-    let mut s: HashSet<usize> = HashSet::new();
-    for row in out.iter() {
-        if row.len() != len {
-            return Err("not a square");
-        }
-        for e in row.iter() {
-            if s.contains(e) {
-                return Err("duplicate value");
-            }
-            s.insert(*e);
-        }
-        height += 1;
+}
+
+fn pick_heuristic(matches: &ArgMatches, goal: &Board) -> Box<dyn Heuristic> {
+    match matches.value_of("heuristic").unwrap_or("manhattan") {
+        "hamming" => Box::new(Hamming),
+        "ool" => Box::new(OutOfLine::new(goal)),
+        // Its sequence score follows the snail, so any other goal is never reached at 0
+        "nilsson" if *goal != snail_goal(goal.len(), goal.len()) => Error::with_description(
+            "the nilsson heuristic only works on square boards with the snail goal",
+            ErrorKind::InvalidValue,
+        )
+        .exit(),
+        "nilsson" => Box::new(Nilsson),
+        "custom" => Box::new(Custom),
+        "linear" => Box::new(LinearConflict::new(goal)),
+        "pdb" => Box::new(open_pdb(matches, goal)),
+        "walking" if goal.len().max(goal[0].len()) > walking::MAX_LEN => Error::with_description(
+            "walking distance only goes up to 4x4",
+            ErrorKind::InvalidValue,
+        )
+        .exit(),
+        "walking" => Box::new(WalkingDistance::new(goal)),
+        _ => Box::new(Manhattan::new(goal)),
     }
-    if height != len {
-        return Err("not a square");
+}
+
+// The heuristic for searching back from puzzle to the start. A pattern database aimed at
+// one board is no use for any other, so it's built in memory rather than cached
+fn backward_heuristic(matches: &ArgMatches, puzzle: &Board) -> Box<dyn Heuristic> {
+    if matches.value_of("heuristic") != Some("pdb") {
+        return pick_heuristic(matches, puzzle);
     }
-    for i in 0..(len * len) {
-        if !s.contains(&i) {
-            return Err("invalid value");
-        }
+    match Pdb::build(puzzle, partition(matches, puzzle.len(), puzzle[0].len())) {
+        Ok(db) => Box::new(db),
+        Err(e) => Error::with_description(e, ErrorKind::InvalidValue).exit(),
     }
-    Ok(out)
 }
 
-fn construct_basic_goal(n: usize) -> Vec<Vec<usize>> {
-    let mut goal = Vec::new();
-    for _ in 0..n {
-        let mut row = Vec::new();
-        for _ in 0..n {
-            row.push(0);
-        }
-        goal.push(row);
+// Exit codes, see the README; a batch exits with the highest of its puzzles'
+const EXIT_INVALID: i32 = 1;
+const EXIT_INSOLUBLE: i32 = 2;
+const EXIT_EXHAUSTED: i32 = 3;
+const EXIT_LIMIT: i32 = 4;
+// A solution that doesn't hold up, whether replayed or straight from the solver
+const EXIT_UNVERIFIED: i32 = 5;
+
+// Tallies over every puzzle in a batch
+#[derive(Default)]
+struct Totals {
+    puzzles: usize,
+    solved: usize,
+    insoluble: usize,
+    invalid: usize,
+    exhausted: usize,
+    limited: usize,
+    steps: usize,
+    time: usize,
+    space: usize,
+}
+
+// A path of - reads standard input
+fn read_input(path: &str) -> String {
+    let mut contents = String::new();
+    let read = if path == "-" {
+        io::stdin().read_to_string(&mut contents)
+    } else {
+        File::open(path).and_then(|mut f| f.read_to_string(&mut contents))
+    };
+    if let Err(e) = read {
+        Error::with_description(&format!("could not read {}: {}", path, e), ErrorKind::Io).exit();
+    }
+    contents
+}
+
+fn read_board(path: &str) -> Board {
+    match parse_input(read_input(path)) {
+        Ok(board) => board,
+        Err(e) => Error::with_description(&format!("{}: {}", path, e), ErrorKind::InvalidValue)
+            .exit(),
     }
-    let mut base = 0;
-    for i in 0..(n / 2) {
-        for j in i..(n - i - 1) {
-            let common = base + j - i + 1;
-            let diff = n - 2 * i - 1;
-            goal[i][j] = common;
-            goal[j][n - i - 1] = common + diff;
-            goal[n - i - 1][n - j - 1] = common + 2 * diff;
-            goal[n - j - 1][i] = common + 3 * diff;
+}
+
+fn pick_goal(matches: &ArgMatches, rows: usize, cols: usize) -> Board {
+    match matches.value_of("goal").unwrap_or("snail") {
+        "snail" => snail_goal(rows, cols),
+        "row-major" => row_major_goal(rows, cols),
+        "blank-first" => blank_first_goal(rows, cols),
+        path => {
+            let goal = read_board(path);
+            if goal.len() != rows || goal[0].len() != cols {
+                Error::with_description(
+                    &format!(
+                        "goal is {}x{} but the puzzle is {}x{}",
+                        goal.len(),
+                        goal[0].len(),
+                        rows,
+                        cols
+                    ),
+                    ErrorKind::InvalidValue,
+                )
+                .exit();
+            }
+            goal
         }
-        base += (n - 2 * i - 1) * 4;
     }
-    goal[n / 2][(n - 1) / 2] = 0;
-    goal
 }
 
-fn refine(puzzle: Vec<Vec<usize>>, heur: Heuristic, greedy: bool) -> Quest {
-    let n = puzzle.len();
-    let goal = construct_basic_goal(n);
-    Quest::new(puzzle, heur, greedy, goal)
+fn generate_pdb(matches: &ArgMatches) {
+    let len = matches.value_of("SIZE").unwrap().parse::<usize>().unwrap();
+    let goal = pick_goal(matches, len, len);
+    let partition = partition(matches, len, len);
+    let path = pdb::cache_dir(matches.value_of("cache-dir")).join(Pdb::file_name(&goal, partition));
+    let db = match Pdb::build(&goal, partition) {
+        Ok(db) => db,
+        Err(e) => Error::with_description(e, ErrorKind::InvalidValue).exit(),
+    };
+    db.save(&path).expect("could not write pattern database");
+    println!("{}", path.display());
 }
 
-fn solverize(mut quest: Quest) -> Vec<(usize, usize)> {
-    while quest.continues() {
-        let out = match quest.step() {
-            Some(output) => output,
-            None => continue,
+fn bad_value(name: &str, value: &str) -> ! {
+    let msg = format!("`{}` is not a valid value for --{}", value, name);
+    Error::with_description(&msg, ErrorKind::InvalidValue).exit()
+}
+
+// Timeouts are in seconds, memory in bytes with an optional K, M or G suffix
+fn limits(matches: &ArgMatches) -> Limits {
+    let max_nodes = matches
+        .value_of("max-nodes")
+        .map(|value| value.parse().unwrap_or_else(|_| bad_value("max-nodes", value)));
+    let timeout = matches.value_of("timeout").map(|value| match value.parse::<f64>() {
+        Ok(secs) if secs >= 0.0 && secs.is_finite() => Duration::from_millis((secs * 1e3) as u64),
+        _ => bad_value("timeout", value),
+    });
+    let max_memory = matches.value_of("max-memory").map(|value| {
+        let (digits, scale) = match value.chars().last() {
+            Some('K') | Some('k') => (&value[..value.len() - 1], 1 << 10),
+            Some('M') | Some('m') => (&value[..value.len() - 1], 1 << 20),
+            Some('G') | Some('g') => (&value[..value.len() - 1], 1 << 30),
+            _ => (value, 1),
         };
-        println!("space: {}", quest.space());
-        println!("time: {}", quest.time());
-        println!("steps: {}", out.steps().len() - 1);
-        println!("dist: {}", out.dist());
-        return out.steps().clone();
+        match digits.parse::<usize>().ok().and_then(|n| n.checked_mul(scale)) {
+            Some(bytes) => bytes,
+            None => bad_value("max-memory", value),
+        }
+    });
+    Limits { max_nodes, timeout, max_memory }
+}
+
+fn millis(wall: Duration) -> f64 {
+    wall.as_secs() as f64 * 1000.0 + wall.subsec_nanos() as f64 / 1e6
+}
+
+// What a run cost and what it found, for json output
+fn stats(solution: &Solution, wall: Duration) -> Vec<(&'static str, String)> {
+    let ms = millis(wall);
+    vec![
+        ("moves", json_string(&solution.moves())),
+        ("length", solution.len().to_string()),
+        ("optimal", solution.optimal.to_string()),
+        ("bound", solution.bound.map_or("null".to_string(), json_number)),
+        ("max_open", solution.space.to_string()),
+        ("expanded", solution.time.to_string()),
+        ("dist", solution.dist.to_string()),
+        ("wall_ms", format!("{:.3}", ms)),
+    ]
+}
+
+// Never hands out a solution that doesn't hold up on its own
+fn confirm(puzzle: &Board, goal: &Board, solution: &Solution) {
+    if let Err(e) = verify(puzzle, goal, &solution.steps) {
+        eprintln!("error: the solver returned a bad solution: {}", e);
+        std::process::exit(EXIT_UNVERIFIED);
     }
-    println!("Unstackable cups!");
-    return Vec::new();
 }
 
-fn insoluble(board: &Vec<Vec<usize>>, goal: Option<Vec<Vec<usize>>>) -> bool {
-    let len = board.len();
-    let n = len * len;
-    let mut inv_board = 0;
-    let mut inv_goal = 0;
-    let mut zero_row_board = 0;
-    let mut zero_row_goal = 0;
-    let mut weights_board: Vec<usize> = std::iter::repeat(0).take(n).collect();
-    let mut weights_goal = weights_board.clone();
-    let goal = if let Some(goal) = goal {
-        goal
-    } else {
-        construct_basic_goal(len)
+// Streams each better solution an anytime search finds, ahead of the final one
+fn improved(number: &str, solution: &Solution, wall: Duration, json: bool) {
+    if json {
+        let status = [("puzzle", number.to_string()), ("status", json_string("improved"))];
+        json_line(&[&status[..], &stats(solution, wall)].concat());
+        return;
+    }
+    let bound = match solution.bound {
+        Some(bound) => format!(", at most {:.3}x optimal", bound),
+        None => String::new(),
     };
-    for i in 0..len {
-        for j in 0..len {
-            inv_board += weights_board[board[i][j]];
-            inv_goal += weights_goal[goal[i][j]];
-            if board[i][j] == 0 {
-                zero_row_board = i;
-            }
-            if goal[i][j] == 0 {
-                zero_row_goal = i;
-            }
-            for k in 1..board[i][j] {
-                weights_board[k] += 1;
-            }
-            for k in 1..goal[i][j] {
-                weights_goal[k] += 1;
-            }
-        }
+    println!("improved: {} moves{} after {:.3} ms", solution.len(), bound, millis(wall));
+}
+
+fn report(totals: &Totals, json: bool) {
+    if json {
+        json_line(&[
+            ("puzzles", totals.puzzles.to_string()),
+            ("solved", totals.solved.to_string()),
+            ("insoluble", totals.insoluble.to_string()),
+            ("invalid", totals.invalid.to_string()),
+            ("exhausted", totals.exhausted.to_string()),
+            ("limit_reached", totals.limited.to_string()),
+            ("total_steps", totals.steps.to_string()),
+            ("total_expanded", totals.time.to_string()),
+            ("max_open", totals.space.to_string()),
+        ]);
+        return;
     }
-    let check =
-        (inv_board + inv_goal + ((zero_row_board + zero_row_goal) % 2) * ((len + 1) % 2)) % 2;
-    if check == 0 {
-        false
-    } else {
-        true
+    println!("puzzles: {}", totals.puzzles);
+    println!("solved: {}", totals.solved);
+    println!("insoluble: {}", totals.insoluble);
+    println!("invalid: {}", totals.invalid);
+    println!("exhausted: {}", totals.exhausted);
+    println!("limit reached: {}", totals.limited);
+    println!("total steps: {}", totals.steps);
+    println!("total time: {}", totals.time);
+    println!("max space: {}", totals.space);
+}
+
+fn summarize(solution: &Solution) {
+    println!("space: {}", solution.space);
+    println!("time: {}", solution.time);
+    println!("steps: {}", solution.len());
+    println!("dist: {}", solution.dist);
+    println!("optimal: {}", solution.optimal);
+    if let Some(bound) = solution.bound.filter(|&bound| bound > 1.0) {
+        println!("bound: at most {:.3}x optimal", bound);
     }
+    println!("moves: {}", solution.moves());
 }
 
-fn puzzle_gen(len: usize) -> Vec<Vec<usize>> {
-    let mut arr = Vec::with_capacity(len * len);
-    for x in 0..(len * len) {
-        arr.push(x);
+fn narrate(moves: &str) {
+    for letter in moves.chars() {
+        match letter {
+            'U' => println!("Slide up!"),
+            'D' => println!("Slide down!"),
+            'L' => println!("Slide left!"),
+            _ => println!("Slide right!"),
+        }
     }
-    thread_rng().shuffle(&mut arr);
-    let mut out = Vec::with_capacity(len);
-    for _ in 0..len {
-        let mut row = Vec::with_capacity(len);
-        for _ in 0..len {
-            row.push(arr.pop().unwrap());
+}
+
+fn replay(matches: &ArgMatches) {
+    let puzzle = read_board(matches.value_of("INPUT").unwrap());
+    let goal = pick_goal(matches, puzzle.len(), puzzle[0].len());
+    let end = match moves::replay(&puzzle, matches.value_of("MOVES").unwrap()) {
+        Ok(end) => end,
+        Err(e @ MoveError::Illegal { .. }) => {
+            eprintln!("error: {}", e);
+            std::process::exit(EXIT_UNVERIFIED);
         }
-        out.push(row);
+        Err(e) => Error::with_description(&e.to_string(), ErrorKind::InvalidValue).exit(),
+    };
+    for row in end.iter() {
+        println!("{:?}", row);
+    }
+    println!("reaches goal: {}", end == goal);
+    if end != goal {
+        std::process::exit(EXIT_UNVERIFIED);
     }
-    let mut tmp = File::create("puzzles/tmp.txt").unwrap();
-    for row in out.iter() {
-        for e in row.iter() {
-            write!(tmp, "{} ", e).expect("Could not write to tmp file");
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
         }
-        tmp.write(b"\n").expect("Could not write to tmp file");
     }
-    if insoluble(&out, None) {
-        puzzle_gen(len)
+    out.push('"');
+    out
+}
+
+// Json has no infinity or NaN, so those come out as null
+fn json_number(x: f64) -> String {
+    if x.is_finite() {
+        x.to_string()
     } else {
-        out
+        "null".to_string()
     }
 }
 
+// One object per line, from values that are already encoded
+fn json_line(fields: &[(&str, String)]) {
+    let body: Vec<String> =
+        fields.iter().map(|(key, value)| format!("{}:{}", json_string(key), value)).collect();
+    println!("{{{}}}", body.join(","));
+}
+
+// Square tiles, with the longer side of the board WIDTH across. Fails without a display,
+// as on servers and CI
+#[cfg(feature = "gui")]
+fn open_window(board: &Board) -> Result<Window, String> {
+    let (rows, cols) = (board.len() as u32, board[0].len() as u32);
+    let tile = WIDTH / rows.max(cols);
+    WindowSettings::new("NPuzzle", [tile * cols, tile * rows])
+        .opengl(OpenGL::V3_2)
+        .exit_on_esc(true)
+        .build()
+}
+
+#[cfg(feature = "gui")]
+fn play(puzzle: Board, goal: Board) {
+    let window = open_window(&goal).unwrap_or_else(|e| {
+        let msg = format!("could not open a window to play in: {}", e);
+        Error::with_description(&msg, ErrorKind::Io).exit()
+    });
+    let game = Game::new(GlGraphics::new(OpenGL::V3_2), puzzle, goal, WIDTH);
+    let_me_try(game, window);
+}
+
+#[cfg(not(feature = "gui"))]
+fn play(_puzzle: Board, _goal: Board) {
+    Error::with_description(
+        "npuzzle was built without the gui feature",
+        ErrorKind::InvalidValue,
+    )
+    .exit();
+}
+
+#[cfg(feature = "gui")]
+fn show(steps: Vec<(usize, usize)>, puzzle: Board, goal: Board) {
+    let window = match open_window(&goal) {
+        Ok(window) => window,
+        Err(e) => {
+            eprintln!("could not open a window ({}), narrating instead", e);
+            return narrate(&moves::to_moves(&steps));
+        }
+    };
+    let viz = Viz::new(GlGraphics::new(OpenGL::V3_2), steps, puzzle, &goal, WIDTH);
+    do_it(viz, window);
+}
+
+#[cfg(not(feature = "gui"))]
+fn show(steps: Vec<(usize, usize)>, _puzzle: Board, _goal: Board) {
+    narrate(&moves::to_moves(&steps));
+}
+
+#[cfg(feature = "gui")]
 fn let_me_try(mut game: Game, mut window: Window) {
     let mut events = Events::new(EventSettings::new());
     let ufps = 8;
@@ -214,6 +416,7 @@ fn let_me_try(mut game: Game, mut window: Window) {
     }
 }
 
+#[cfg(feature = "gui")]
 fn do_it(mut viz: Viz, mut window: Window) {
     let mut events = Events::new(EventSettings::new());
     let ufps = 8;
@@ -238,11 +441,23 @@ fn main() {
             Arg::with_name("greedy")
                 .short("g")
                 .long("greedy")
-                .help("Sets search to greedy"),
+                .help("Sets search to greedy, ordering boards by the heuristic alone"),
+        )
+        .arg(
+            Arg::with_name("weight")
+                .short("w")
+                .long("weight")
+                .value_name("W")
+                .help(
+                    "Orders A* by g + W * h for W >= 1, finding solutions at most W times \
+                     optimal faster",
+                )
+                .takes_value(true)
+                .conflicts_with("greedy"),
         )
         .arg(
             Arg::with_name("INPUT")
-                .help("Sets the input file to use")
+                .help("Sets the input file to use, or - for standard input")
                 .required(true)
                 .conflicts_with("auto")
                 .index(1),
@@ -253,8 +468,77 @@ fn main() {
                 .long("heuristic")
                 .help("Sets the heuristic")
                 .takes_value(true)
-                .possible_values(&["manhattan", "hamming", "ool", "nilsson", "custom"]),
+                .possible_values(&[
+                    "manhattan",
+                    "hamming",
+                    "ool",
+                    "nilsson",
+                    "custom",
+                    "linear",
+                    "pdb",
+                    "walking",
+                ]),
+        )
+        .arg(
+            Arg::with_name("algorithm")
+                .long("algorithm")
+                .help(
+                    "Sets the search algorithm; bidir searches from both ends, building a \
+                     second heuristic for every board",
+                )
+                .takes_value(true)
+                .possible_values(&["astar", "ida", "bidir"]),
+        )
+        .arg(
+            Arg::with_name("goal")
+                .long("goal")
+                .help("Sets the goal layout: snail, row-major, blank-first, or a goal file")
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("partition")
+                .long("partition")
+                .help("Sets the pattern database tile groups, e.g. 6-6-3")
+                .takes_value(true)
+                .global(true),
         )
+        .arg(
+            Arg::with_name("cache-dir")
+                .long("cache-dir")
+                .help("Sets where pattern databases are stored")
+                .takes_value(true)
+                .global(true),
+        )
+        .subcommand(
+            SubCommand::with_name("generate-pdb")
+                .about("Builds pattern database tables ahead of time")
+                .arg(
+                    Arg::with_name("SIZE")
+                        .help("Sets the board size")
+                        .required(true)
+                        .possible_values(&["2", "3", "4", "5"])
+                        .index(1),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("replay")
+                .alias("verify")
+                .about("Applies a move string to a board and checks it reaches the goal")
+                .arg(
+                    Arg::with_name("INPUT")
+                        .help("Sets the board file to start from, or - for standard input")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("MOVES")
+                        .help("Sets the moves, one of U, D, L or R for the way each tile slides")
+                        .required(true)
+                        .index(2),
+                ),
+        )
+        .setting(AppSettings::SubcommandsNegateReqs)
         .arg(
             Arg::with_name("auto")
                 .short("a")
@@ -263,165 +547,336 @@ fn main() {
                 .takes_value(true)
                 .possible_values(&["2", "3", "4", "5", "6", "7"]),
         )
+        .arg(
+            Arg::with_name("seed")
+                .long("seed")
+                .help("Seeds --auto so the same board comes out every time")
+                .takes_value(true)
+                .requires("auto"),
+        )
+        .arg(
+            Arg::with_name("anytime")
+                .long("anytime")
+                .help(
+                    "Keeps improving on the first solution, printing each better one, until \
+                     it's optimal or --timeout runs out",
+                ),
+        )
+        .arg(
+            Arg::with_name("max-nodes")
+                .long("max-nodes")
+                .value_name("N")
+                .help("Gives up after expanding N nodes")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("timeout")
+                .long("timeout")
+                .value_name("SECS")
+                .help("Gives up after SECS seconds of searching")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("max-memory")
+                .long("max-memory")
+                .value_name("BYTES")
+                .help("Gives up once the search holds about BYTES, e.g. 512M or 2G")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("difficulty")
+                .long("difficulty")
+                .value_name("MOVES")
+                .help(
+                    "Generates a board whose shortest solution is MOVES long; above 3x3, \
+                     boards too deep to confirm are only at least MOVES long",
+                )
+                .takes_value(true)
+                .requires("auto"),
+        )
+        .arg(
+            Arg::with_name("save-generated")
+                .long("save-generated")
+                .value_name("PATH")
+                .help("Writes the --auto board to PATH in the input format")
+                .takes_value(true)
+                .requires("auto"),
+        )
         .arg(
             Arg::with_name("mine")
                 .short("m")
                 .long("mine")
-                .conflicts_with_all(&["quiet", "heuristic", "greedy"])
+                .conflicts_with_all(&["quiet", "heuristic", "greedy", "algorithm", "format"])
                 .help("Lets you take the wheel"),
         )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .help("Sets the output format; json prints one object per puzzle")
+                .takes_value(true)
+                .possible_values(&["text", "json"]),
+        )
         .arg(
             Arg::with_name("quiet")
                 .short("q")
                 .long("quiet")
-                .help("Suppresses visualizer"),
+                .help("Suppresses visualizer, leaving the summary and move string"),
         )
         .get_matches();
-    let puzzle = if matches.is_present("auto") {
-        puzzle_gen(
-            matches
-                .value_of("auto")
-                .unwrap()
-                .parse::<usize>()
-                .unwrap_or(3),
-        )
-    } else {
-        let mut f = File::open(matches.value_of("INPUT").unwrap()).expect("could not open file");
-        let mut contents = String::new();
-        f.read_to_string(&mut contents)
-            .expect("could not read file");
-        parse_input(contents).expect("invalid puzzle")
-    };
+    if let Some(matches) = matches.subcommand_matches("generate-pdb") {
+        generate_pdb(matches);
+        return;
+    }
+    if let Some(matches) = matches.subcommand_matches("replay") {
+        replay(matches);
+        return;
+    }
     let greedy = matches.is_present("greedy");
-    let heur = match matches.value_of("heuristic").unwrap_or("manhattan") {
-        "manhattan" => Heuristic::Manhattan,
-        "hamming" => Heuristic::Hamming,
-        "ool" => Heuristic::OutOfLine,
-        "nilsson" => Heuristic::Nilsson,
-        "custom" => Heuristic::Custom,
-        _ => Heuristic::Manhattan,
+    let anytime = matches.is_present("anytime");
+    // Greedy search is weighted A* taken all the way
+    let weight = match matches.value_of("weight") {
+        _ if greedy => f64::INFINITY,
+        Some(value) => match value.parse::<f64>() {
+            // Below 1 would only make the search slower than plain A*, for no better answer
+            Ok(weight) if weight.is_finite() && weight >= 1.0 => weight,
+            _ => bad_value("weight", value),
+        },
+        // Anytime search starts out weighted so there's something to improve on
+        None if anytime => 2.0,
+        None => 1.0,
     };
-    let quest = refine(puzzle.clone(), heur, greedy);
-    for row in puzzle.iter() {
-        println!("{:?}", row);
+    let algorithm = matches.value_of("algorithm").unwrap_or("astar");
+    let algo = match algorithm {
+        "ida" => Algorithm::Ida,
+        "bidir" => Algorithm::Bidirectional,
+        _ if anytime => Algorithm::Anytime,
+        _ => Algorithm::AStar,
+    };
+    if (weight != 1.0 || anytime) && algorithm != "astar" {
+        Error::with_description(
+            "greedy, weighted and anytime search are only available with --algorithm astar",
+            ErrorKind::ArgumentConflict,
+        )
+        .exit();
     }
-    if quest.insoluble() {
-        println!("Unstackable cups!");
-    } else {
-        let width = 500;
-        let opengl = OpenGL::V3_2;
-        let window: Window = WindowSettings::new("NPuzzle", [width, width])
-            .opengl(opengl)
-            .exit_on_esc(true)
-            .build()
-            .unwrap();
-        if matches.is_present("mine") {
-            let game = Game::new(GlGraphics::new(opengl), puzzle, quest.get_goal(), width);
-            let_me_try(game, window);
-        } else {
-            let goal = quest.get_goal();
-            let steps = solverize(quest);
-            if matches.is_present("quiet") {
-                for i in 0..(steps.len()) {
-                    if i != 0 {
-                        match (
-                            steps[i].0 as i64 - steps[i - 1].0 as i64,
-                            steps[i].1 as i64 - steps[i - 1].1 as i64,
-                        ) {
-                            (-1, 0) => println!("Slide down!"),
-                            (1, 0) => println!("Slide up!"),
-                            (0, -1) => println!("Slide right!"),
-                            (0, 1) => println!("Slide left!"),
-                            _ => println!("A hop, skip, or jump"),
-                        }
+    if algo == Algorithm::Bidirectional && matches.value_of("heuristic") == Some("nilsson") {
+        Error::with_description(
+            "the nilsson heuristic only aims at the snail goal, so it can't search backwards",
+            ErrorKind::ArgumentConflict,
+        )
+        .exit();
+    }
+    let puzzles = if matches.is_present("auto") {
+        let len = matches
+            .value_of("auto")
+            .unwrap()
+            .parse::<usize>()
+            .unwrap_or(3);
+        let seed = match matches.value_of("seed") {
+            Some(seed) => seed.parse::<u64>().unwrap_or_else(|_| {
+                let msg = format!("`{}` is not a valid seed", seed);
+                Error::with_description(&msg, ErrorKind::InvalidValue).exit()
+            }),
+            None => thread_rng().gen(),
+        };
+        // Kept off stdout so json output stays one object per line
+        eprintln!("seed: {}", seed);
+        let mut rng = seeded_rng(seed);
+        let goal = pick_goal(&matches, len, len);
+        let puzzle = match matches.value_of("difficulty") {
+            Some(depth) => {
+                let depth = depth.parse::<usize>().unwrap_or_else(|_| {
+                    let msg = format!("`{}` is not a valid difficulty", depth);
+                    Error::with_description(&msg, ErrorKind::InvalidValue).exit()
+                });
+                match puzzle_at_depth(&goal, depth, &mut rng) {
+                    Some(Generated::Exact(board)) => board,
+                    Some(Generated::AtLeast(board)) => {
+                        eprintln!(
+                            "too deep to confirm: the board is at least {} moves out, \
+                             maybe more",
+                            depth
+                        );
+                        board
+                    }
+                    // Only a breadth first search covers every board, so only it can be sure
+                    None if len * len <= EXACT_CELLS => {
+                        let msg =
+                            format!("no {}x{} board is {} moves from the goal", len, len, depth);
+                        Error::with_description(&msg, ErrorKind::InvalidValue).exit()
+                    }
+                    None => {
+                        let msg = format!(
+                            "linear conflict never reached {} on a {}x{} board",
+                            depth, len, len
+                        );
+                        Error::with_description(&msg, ErrorKind::InvalidValue).exit()
                     }
                 }
-            } else {
-                let viz = Viz::new(GlGraphics::new(opengl), steps, puzzle, &goal, width);
-                do_it(viz, window);
+            }
+            None => puzzle_gen(&goal, &mut rng),
+        };
+        if let Some(path) = matches.value_of("save-generated") {
+            if let Err(e) = File::create(path).and_then(|mut f| write_board(&mut f, &puzzle)) {
+                let msg = format!("could not save to {}: {}", path, e);
+                Error::with_description(&msg, ErrorKind::Io).exit();
             }
         }
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn reject_empty() {
-        let mut f = File::open("puzzles/parsing/empty.txt").expect("could not open file");
-        let mut contents = String::new();
-        f.read_to_string(&mut contents)
-            .expect("could not read file");
-        assert_eq!("no input", parse_input(contents).expect_err("returned"));
-    }
-
-    #[test]
-    fn reject_oblong() {
-        let mut f = File::open("puzzles/parsing/oblong.txt").expect("could not open file");
-        let mut contents = String::new();
-        f.read_to_string(&mut contents)
-            .expect("could not read file");
-        assert_eq!("not a square", parse_input(contents).expect_err("returned"));
-    }
-
-    #[test]
-    fn reject_rect() {
-        let mut f = File::open("puzzles/parsing/rect.txt").expect("could not open file");
-        let mut contents = String::new();
-        f.read_to_string(&mut contents)
-            .expect("could not read file");
-        assert_eq!("not a square", parse_input(contents).expect_err("returned"));
-    }
-
-    #[test]
-    fn reject_invalid() {
-        let mut f = File::open("puzzles/parsing/wrong_nums.txt").expect("could not open file");
-        let mut contents = String::new();
-        f.read_to_string(&mut contents)
-            .expect("could not read file");
-        assert_eq!(
-            "invalid value",
-            parse_input(contents).expect_err("returned")
-        );
-    }
-
-    #[test]
-    fn reject_dup() {
-        let mut f = File::open("puzzles/parsing/dups.txt").expect("could not open file");
-        let mut contents = String::new();
-        f.read_to_string(&mut contents)
-            .expect("could not read file");
-        assert_eq!(
-            "duplicate value",
-            parse_input(contents).expect_err("returned")
-        );
-    }
-
-    #[test]
-    fn accept_valid() {
-        let mut f = File::open("puzzles/parsing/clean.txt").expect("could not open file");
-        let mut contents = String::new();
-        f.read_to_string(&mut contents)
-            .expect("could not read file");
-        let puzzle = parse_input(contents).expect("Error");
-        assert!(puzzle.len() == 3);
-        for i in 0..3 {
-            assert!(puzzle[i].len() == 3);
+        vec![Ok(puzzle)]
+    } else {
+        let path = matches.value_of("INPUT").unwrap();
+        let puzzles = parse_batch(&read_input(path));
+        if let [Err(e)] = &puzzles[..] {
+            Error::with_description(&format!("{}: {}", path, e), ErrorKind::InvalidValue).exit();
         }
-    }
-
-    #[test]
-    fn accept_comments() {
-        let mut f = File::open("puzzles/parsing/comments.txt").expect("could not open file");
-        let mut contents = String::new();
-        f.read_to_string(&mut contents)
-            .expect("could not read file");
-        let puzzle = parse_input(contents).expect("Error");
-        assert!(puzzle.len() == 3);
-        for i in 0..3 {
-            assert!(puzzle[i].len() == 3);
+        puzzles
+    };
+    let batch = puzzles.len() > 1;
+    let json = matches.value_of("format") == Some("json");
+    let name = matches.value_of("heuristic").unwrap_or("manhattan");
+    let limits = limits(&matches);
+    // Heuristics can be costly to set up, so puzzles sharing a goal share one
+    let mut heuristics: HashMap<Board, Box<dyn Heuristic>> = HashMap::new();
+    let mut totals = Totals::default();
+    let mut code = 0;
+    for (i, puzzle) in puzzles.into_iter().enumerate() {
+        totals.puzzles += 1;
+        let number = (i + 1).to_string();
+        if batch && !json {
+            println!("puzzle {}", number);
+        }
+        let puzzle = match puzzle {
+            Ok(puzzle) => puzzle,
+            Err(e) => {
+                totals.invalid += 1;
+                code = code.max(EXIT_INVALID);
+                if json {
+                    let error = ("error", json_string(&e.to_string()));
+                    json_line(&[("puzzle", number), ("status", json_string("invalid")), error]);
+                } else {
+                    println!("invalid: {}", e);
+                }
+                continue;
+            }
+        };
+        let goal = pick_goal(&matches, puzzle.len(), puzzle[0].len());
+        let boards = [("board", format!("{:?}", puzzle)), ("goal", format!("{:?}", goal))];
+        if !json {
+            for row in puzzle.iter() {
+                println!("{:?}", row);
+            }
         }
+        let start = Instant::now();
+        // Checked up front so insoluble boards never wait on a pattern database being built
+        let outcome = match parity(&puzzle, &goal) {
+            Some(parity) => Outcome::Insoluble(parity),
+            None if matches.is_present("mine") => {
+                play(puzzle, goal);
+                continue;
+            }
+            None => {
+                let heur = heuristics
+                    .entry(goal.clone())
+                    .or_insert_with(|| pick_heuristic(&matches, &goal));
+                // Searching backwards needs a heuristic aimed at each start board
+                let backward = match algo {
+                    Algorithm::Bidirectional => Some(backward_heuristic(&matches, &puzzle)),
+                    _ => None,
+                };
+                let report = |solution: &Solution| {
+                    confirm(&puzzle, &goal, solution);
+                    improved(&number, solution, start.elapsed(), json)
+                };
+                let options = Options {
+                    algorithm: algo,
+                    weight,
+                    limits,
+                    backward,
+                    report: Some(Box::new(report)),
+                };
+                search(puzzle.clone(), goal.clone(), &**heur, options)
+            }
+        };
+        let wall = start.elapsed();
+        let run = [
+            ("heuristic", json_string(name)),
+            ("algorithm", json_string(algorithm)),
+            ("greedy", greedy.to_string()),
+            ("weight", json_number(weight)),
+        ];
+        let solution = match outcome {
+            Outcome::Solved(solution) => solution,
+            Outcome::LimitReached { limit, best } => {
+                totals.limited += 1;
+                code = code.max(EXIT_LIMIT);
+                if json {
+                    let status = [
+                        ("puzzle", number),
+                        ("status", json_string("limit_reached")),
+                        ("limit", json_string(&limit.to_string())),
+                    ];
+                    json_line(&[&status[..], &boards[..], &run[..], &stats(&best, wall)].concat());
+                } else {
+                    println!("{} limit reached, closest board found:", limit);
+                    for row in moves::replay(&puzzle, &best.moves()).unwrap().iter() {
+                        println!("{:?}", row);
+                    }
+                    summarize(&best);
+                }
+                continue;
+            }
+            Outcome::Insoluble(parity) => {
+                totals.insoluble += 1;
+                code = code.max(EXIT_INSOLUBLE);
+                if json {
+                    let status = [("puzzle", number), ("status", json_string("insoluble"))];
+                    let reason = [("reason", json_string(&parity.to_string()))];
+                    json_line(&[&status[..], &boards[..], &reason[..]].concat());
+                } else {
+                    println!("insoluble: {}", parity);
+                }
+                continue;
+            }
+            Outcome::Invalid(bad) => {
+                totals.invalid += 1;
+                code = code.max(EXIT_INVALID);
+                if json {
+                    let error = ("error", json_string(&bad.to_string()));
+                    json_line(&[("puzzle", number), ("status", json_string("invalid")), error]);
+                } else {
+                    println!("invalid: {}", bad);
+                }
+                continue;
+            }
+            Outcome::SearchExhausted => {
+                totals.exhausted += 1;
+                code = code.max(EXIT_EXHAUSTED);
+                if json {
+                    let status = [("puzzle", number), ("status", json_string("exhausted"))];
+                    json_line(&[&status[..], &boards[..], &run[..]].concat());
+                } else {
+                    println!("search exhausted without reaching the goal");
+                }
+                continue;
+            }
+        };
+        confirm(&puzzle, &goal, &solution);
+        totals.solved += 1;
+        totals.steps += solution.len();
+        totals.time += solution.time;
+        totals.space = totals.space.max(solution.space);
+        if json {
+            let status = [("puzzle", number), ("status", json_string("solved"))];
+            json_line(&[&status[..], &boards[..], &run[..], &stats(&solution, wall)].concat());
+        } else {
+            summarize(&solution);
+            if !matches.is_present("quiet") {
+                show(solution.steps, puzzle, goal);
+            }
+        }
+    }
+    if batch {
+        report(&totals, json);
     }
+    std::process::exit(code);
 }
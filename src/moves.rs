@@ -0,0 +1,76 @@
+use std::error;
+use std::fmt;
+
+use crate::Board;
+
+// Solutions written as the direction each tile slides: U, D, L or R. A tile sliding up
+// means the blank moved down. Whitespace is ignored so long solutions can be wrapped.
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MoveError {
+    NoBlank,
+    /// Steps are counted from 1.
+    Unknown { step: usize, letter: char },
+    Illegal { step: usize, letter: char, blank: (usize, usize) },
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MoveError::NoBlank => write!(f, "the board has no blank"),
+            MoveError::Unknown { step, letter } => {
+                write!(f, "step {}: `{}` is not one of U, D, L or R", step, letter)
+            }
+            MoveError::Illegal { step, letter, blank } => write!(
+                f,
+                "step {}: no tile can slide {} into the blank at row {}, column {}",
+                step,
+                letter,
+                blank.0 + 1,
+                blank.1 + 1
+            ),
+        }
+    }
+}
+
+impl error::Error for MoveError {}
+
+/// Blank positions, as in Solution::steps, to a move string.
+pub fn to_moves(steps: &[(usize, usize)]) -> String {
+    steps
+        .windows(2)
+        .map(|w| match (w[1].0 as i64 - w[0].0 as i64, w[1].1 as i64 - w[0].1 as i64) {
+            (-1, 0) => 'D',
+            (1, 0) => 'U',
+            (0, -1) => 'R',
+            (0, 1) => 'L',
+            _ => panic!("the blank jumped from {:?} to {:?}", w[0], w[1]),
+        })
+        .collect()
+}
+
+/// Applies a move string to board, returning where it ends up.
+pub fn replay(board: &Board, moves: &str) -> Result<Board, MoveError> {
+    let mut board = board.clone();
+    let (rows, cols) = (board.len(), board[0].len());
+    let mut blank = (0..rows * cols)
+        .map(|c| (c / cols, c % cols))
+        .find(|&(i, j)| board[i][j] == 0)
+        .ok_or(MoveError::NoBlank)?;
+    for (i, letter) in moves.chars().filter(|c| !c.is_whitespace()).enumerate() {
+        let step = i + 1;
+        let (y, x) = blank;
+        let from = match letter {
+            'U' if y + 1 < rows => (y + 1, x),
+            'D' if y > 0 => (y - 1, x),
+            'L' if x + 1 < cols => (y, x + 1),
+            'R' if x > 0 => (y, x - 1),
+            'U' | 'D' | 'L' | 'R' => return Err(MoveError::Illegal { step, letter, blank }),
+            _ => return Err(MoveError::Unknown { step, letter }),
+        };
+        board[y][x] = board[from.0][from.1];
+        board[from.0][from.1] = 0;
+        blank = from;
+    }
+    Ok(board)
+}
@@ -1,86 +1,112 @@
 use std::cmp::Ordering;
+use std::mem;
+use std::rc::Rc;
 
+use crate::heuristic::Heuristic;
+use crate::state::State;
 use crate::Direction;
-pub use crate::Heuristic;
 
-#[derive(Clone, Hash, Eq)]
+// Blank positions back to the start, shared between a node and all its descendants
+struct Trail {
+    blank: (u8, u8),
+    prev: Option<Rc<Trail>>,
+}
+
+// Priorities are kept as integers, so weights count to a thousandth
+const SCALE: i64 = 1000;
+
+// Bytes each trail entry takes on the heap, counting the Rc's reference counts
+pub(crate) const TRAIL_BYTES: usize = mem::size_of::<Trail>() + 2 * mem::size_of::<usize>();
+
+#[derive(Clone)]
 pub struct Node {
     f: i64,
     g: i64,
     h: i64,
-    pub path: Vec<(usize, usize)>,
-    board: Vec<Vec<usize>>,
+    state: State,
+    trail: Rc<Trail>,
 }
 
 impl Node {
-    pub fn new(
+    pub fn new<H: Heuristic + ?Sized>(
         board: Vec<Vec<usize>>,
-        heur: Heuristic,
-        greedy: bool,
+        heur: &H,
+        weight: f64,
         goal: &Vec<Vec<usize>>,
     ) -> Node {
-        let mut i = 0;
-        let zero = 'outer: loop {
-            for j in 0..(board.len()) {
-                if board[i][j] == 0 {
-                    break 'outer (i, j);
-                }
-            }
-            i += 1;
-            if i == board.len() {
-                panic!();
-            }
-        };
-        let mut path = Vec::new();
-        path.push(zero);
+        let zero = board
+            .iter()
+            .enumerate()
+            .find_map(|(i, row)| row.iter().position(|&tile| tile == 0).map(|j| (i, j)))
+            .expect("the board has no blank");
+        let trail = Rc::new(Trail {
+            blank: (zero.0 as u8, zero.1 as u8),
+            prev: None,
+        });
         let mut out = Node {
             f: 0,
             g: 0,
             h: 0,
-            path,
-            board,
+            state: State::pack(&board),
+            trail,
         };
-        match heur {
-            Heuristic::Hamming => out.hamming(goal, greedy),
-            Heuristic::Manhattan => out.manhattan(goal, greedy),
-            Heuristic::OutOfLine => out.out_of_line(goal, greedy),
-            Heuristic::Nilsson => out.nilsson(goal, greedy),
-            Heuristic::Custom => out.custom(goal, greedy),
-        }
+        let h = heur.estimate(&board, goal);
+        out.evaluate(h, weight);
         out
     }
 
-    pub(crate) fn shift(
+    pub(crate) fn shift<H: Heuristic + ?Sized>(
         &self,
         dir: Direction,
-        heur: Heuristic,
-        greedy: bool,
+        heur: &H,
+        weight: f64,
         goal: &Vec<Vec<usize>>,
     ) -> Self {
         let mut out = self.clone();
-        out.swap(dir);
-        match heur {
-            Heuristic::Hamming => out.hamming(goal, greedy),
-            Heuristic::Manhattan => out.manhattan(goal, greedy),
-            Heuristic::OutOfLine => out.out_of_line(goal, greedy),
-            Heuristic::Nilsson => out.nilsson(goal, greedy),
-            Heuristic::Custom => out.custom(goal, greedy),
-        }
+        let (tile, from, to) = out.swap(dir);
+        let h = match heur.update(self.h, &out.state, goal, tile, from, to) {
+            Some(h) => {
+                // Checks every incremental update against starting over, which costs the
+                // unpacking and full estimate the update is there to save
+                #[cfg(feature = "check-updates")]
+                {
+                    let full = heur.estimate(&out.state.unpack(), goal);
+                    assert_eq!(h, full, "bad update moving {}", tile);
+                }
+                h
+            }
+            None => heur.estimate(&out.state.unpack(), goal),
+        };
+        out.evaluate(h, weight);
         out
     }
 
-    fn swap(&mut self, dir: Direction) {
-        let &curr = self.path.last().unwrap();
+    // Slides a tile into the blank, returning the tile and where it moved from and to
+    fn swap(&mut self, dir: Direction) -> (usize, (usize, usize), (usize, usize)) {
+        let curr = self.blank();
         let next = match dir {
             Direction::Up => (curr.0 - 1, curr.1),
             Direction::Down => (curr.0 + 1, curr.1),
             Direction::Left => (curr.0, curr.1 - 1),
             Direction::Right => (curr.0, curr.1 + 1),
         };
-        self.board[curr.0][curr.1] = self.board[next.0][next.1];
-        self.board[next.0][next.1] = 0;
-        self.path.push(next);
+        let tile = self.state.slide(next, curr);
+        self.trail = Rc::new(Trail {
+            blank: (next.0 as u8, next.1 as u8),
+            prev: Some(self.trail.clone()),
+        });
         self.inc();
+        (tile, next, curr)
+    }
+
+    // Ordered by g + weight * h, where an infinite weight leaves just h for greedy search
+    fn evaluate(&mut self, h: i64, weight: f64) {
+        self.h = h;
+        if weight.is_infinite() {
+            self.f = -self.h;
+        } else {
+            self.f = -(self.g * SCALE + (weight * (self.h * SCALE) as f64).round() as i64);
+        }
     }
 
     fn inc(&mut self) {
@@ -91,170 +117,52 @@ impl Node {
         self.h
     }
 
-    pub fn steps(&self) -> Vec<(usize, usize)> {
-        self.path.clone()
+    pub fn cost(&self) -> i64 {
+        self.g + self.h
     }
 
-    pub fn board_ref(&self) -> &Vec<Vec<usize>> {
-        &self.board
+    // Moves made to get here
+    pub fn depth(&self) -> i64 {
+        self.g
     }
 
-    pub fn into_board(self) -> Vec<Vec<usize>> {
-        self.board
+    pub fn blank(&self) -> (usize, usize) {
+        (self.trail.blank.0 as usize, self.trail.blank.1 as usize)
     }
 
-    fn _print_board(&self) {
-        for row in self.board.iter() {
-            println!("{:?}", row);
-        }
+    // Where the blank was before the last move
+    pub fn previous(&self) -> Option<(usize, usize)> {
+        let prev = self.trail.prev.as_ref()?;
+        Some((prev.blank.0 as usize, prev.blank.1 as usize))
     }
 
-    fn hamming(&mut self, goal: &Vec<Vec<usize>>, greedy: bool) {
-        self.h = 0;
-        let n = goal.len();
-        for i in 0..n {
-            for j in 0..n {
-                if self.board[i][j] != goal[i][j] {
-                    self.h += 1;
-                }
-            }
-        }
-        if greedy {
-            self.f = -1 * self.h;
-        } else {
-            self.f = -1 * (self.g + self.h);
+    pub fn steps(&self) -> Vec<(usize, usize)> {
+        let mut out = Vec::with_capacity(self.g as usize + 1);
+        let mut trail = Some(&self.trail);
+        while let Some(t) = trail {
+            out.push((t.blank.0 as usize, t.blank.1 as usize));
+            trail = t.prev.as_ref();
         }
+        out.reverse();
+        out
     }
 
-    fn manhattan(&mut self, goal: &Vec<Vec<usize>>, greedy: bool) {
-        self.h = 0;
-        let len = goal.len();
-        let n = len * len;
-        let mut bdp: Vec<(usize, usize)> = std::iter::repeat((0, 0)).take(n).collect();
-        let mut glp = bdp.clone();
-        for i in 0..len {
-            for j in 0..len {
-                bdp[self.board[i][j]] = (i, j);
-                glp[goal[i][j]] = (i, j);
-            }
-        }
-        for i in 0..n {
-            self.h +=
-                (std::cmp::max(bdp[i].0, glp[i].0) - std::cmp::min(bdp[i].0, glp[i].0)) as i64;
-            self.h +=
-                (std::cmp::max(bdp[i].1, glp[i].1) - std::cmp::min(bdp[i].1, glp[i].1)) as i64;
-        }
-        if greedy {
-            self.f = -1 * self.h;
-        } else {
-            self.f = -1 * (self.g + self.h);
-        }
+    pub fn state(&self) -> &State {
+        &self.state
     }
 
-    fn out_of_line(&mut self, goal: &Vec<Vec<usize>>, greedy: bool) {
-        self.h = 0;
-        let len = goal.len();
-        let n = len * len;
-        let mut bdp: Vec<(usize, usize)> = std::iter::repeat((0, 0)).take(n).collect();
-        let mut glp = bdp.clone();
-        for i in 0..len {
-            for j in 0..len {
-                bdp[self.board[i][j]] = (i, j);
-                glp[goal[i][j]] = (i, j);
-            }
-        }
-        for i in 0..n {
-            self.h += match (bdp[i].0 == glp[i].0, bdp[i].1 == glp[i].1) {
-                (true, true) => 0,
-                (true, false) | (false, true) => 1,
-                (false, false) => 2,
-            };
-        }
-        if greedy {
-            self.f = -1 * self.h;
-        } else {
-            self.f = -1 * (self.g + self.h);
-        }
+    // Whether this is the goal, packed as target, whatever the heuristic says
+    pub fn reached(&self, target: &State) -> bool {
+        self.state == *target
     }
 
-    fn nilsson(&mut self, goal: &Vec<Vec<usize>>, greedy: bool) {
-        self.h = 0;
-        let len = goal.len();
-        let n = len * len;
-        let mut bdp: Vec<(usize, usize)> = std::iter::repeat((0, 0)).take(n).collect();
-        let mut glp = bdp.clone();
-        for i in 0..len {
-            for j in 0..len {
-                bdp[self.board[i][j]] = (i, j);
-                glp[goal[i][j]] = (i, j);
-            }
-        }
-        for i in 0..n {
-            self.h +=
-                (std::cmp::max(bdp[i].0, glp[i].0) - std::cmp::min(bdp[i].0, glp[i].0)) as i64;
-            self.h +=
-                (std::cmp::max(bdp[i].1, glp[i].1) - std::cmp::min(bdp[i].1, glp[i].1)) as i64;
-        }
-        for i in 0..(len / 2) {
-            for j in i..(len - i - 1) {
-                if (self.board[i][j] != 0 && self.board[i][j] != n - 1)
-                    && self.board[i][j] + 1 != self.board[i][j + 1]
-                {
-                    self.h += 6;
-                }
-                if (self.board[j][len - i - 1] != 0 && self.board[j][len - i - 1] != n - 1)
-                    && self.board[j][len - i - 1] + 1 != self.board[j + 1][len - i - 1]
-                {
-                    self.h += 6;
-                }
-                if (self.board[len - i - 1][len - j - 1] != 0
-                    && self.board[len - i - 1][len - j - 1] != n - 1)
-                    && self.board[len - i - 1][len - j - 1] + 1
-                        != self.board[len - i - 1][len - j - 2]
-                {
-                    self.h += 6;
-                }
-                if (self.board[len - j - 1][i] != 0 && self.board[len - j - 1][i] != n - 1)
-                    && self.board[len - j - 1][i] + 1 != self.board[len - j - 2][i]
-                {
-                    if i == (len - 1) / 2 {
-                        self.h += 6;
-                    }
-                }
-            }
-        }
-        if self.board[len / 2][(len - 1) / 2] != 0 {
-            self.h += 3;
-        }
-        if greedy {
-            self.f = -1 * self.h;
-        } else {
-            self.f = -1 * (self.g + self.h);
-        }
+    pub fn into_state(self) -> State {
+        self.state
     }
 
-    fn custom(&mut self, goal: &Vec<Vec<usize>>, greedy: bool) {
-        self.h = 0;
-        let len = goal.len();
-        let n = len * len;
-        let mut bdp: Vec<(usize, usize)> = std::iter::repeat((0, 0)).take(n).collect();
-        let mut glp = bdp.clone();
-        for i in 0..len {
-            for j in 0..len {
-                bdp[self.board[i][j]] = (i, j);
-                glp[goal[i][j]] = (i, j);
-            }
-        }
-        for i in 0..n {
-            let tmp = (bdp[i].0 as i64 - glp[i].0 as i64).abs()
-                + (bdp[i].1 as i64 - glp[i].1 as i64).abs();
-            let to_add = 10 * tmp;
-            self.h += to_add;
-        }
-        if greedy {
-            self.f = -1 * self.h;
-        } else {
-            self.f = -1 * (self.g + self.h);
+    fn _print_board(&self) {
+        for row in self.state.unpack().iter() {
+            println!("{:?}", row);
         }
     }
 }
@@ -276,6 +184,8 @@ impl PartialOrd for Node {
 }
 
 // ???? Maybe just make it normal
+impl Eq for Node {}
+
 impl PartialEq for Node {
     fn eq(&self, other: &Node) -> bool {
         self.f == other.f && self.g == other.g
@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::io::{self, Write};
+
+use crate::state::MAX_CELLS;
+use crate::Board;
+
+/// Where a problem was found, 1-based, along with the offending line for quoting.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+    pub text: String,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ParseError {
+    /// Nothing but blank lines and comments.
+    Empty,
+    NotANumber { at: Location, token: String },
+    /// Tiles run from 0 to one less than the number of cells.
+    OutOfRange { at: Location, value: usize, cells: usize },
+    Duplicate { at: Location, value: usize, first: Location },
+    /// A row shorter than the first one, or than the size header.
+    Missing { at: Location, expected: usize, found: usize },
+    /// A row longer than the first one, or than the size header.
+    Ragged { at: Location, expected: usize, found: usize },
+    /// More or fewer rows than the size header declared.
+    RowCount { at: Location, expected: usize, found: usize },
+    /// More cells than MAX_CELLS.
+    TooBig { cells: usize },
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+impl Location {
+    // The line itself with a caret under the column
+    fn quote(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let margin = self.line.to_string().len();
+        writeln!(f)?;
+        writeln!(f, "{} | {}", self.line, self.text)?;
+        write!(f, "{:margin$} | {:>column$}", "", "^", margin = margin, column = self.column)
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Empty => write!(f, "no input"),
+            ParseError::NotANumber { at, token } => {
+                write!(f, "{}: `{}` is not a number", at, token)?;
+                at.quote(f)
+            }
+            ParseError::OutOfRange { at, value, cells } => {
+                write!(f, "{}: {} is out of range, tiles go from 0 to {}", at, value, cells - 1)?;
+                at.quote(f)
+            }
+            ParseError::Duplicate { at, value, first } => {
+                write!(f, "{}: duplicate value {}, first seen at {}", at, value, first)?;
+                at.quote(f)
+            }
+            ParseError::Missing { at, expected, found } => {
+                write!(f, "{}: missing values, expected {} but found {}", at, expected, found)?;
+                at.quote(f)
+            }
+            ParseError::Ragged { at, expected, found } => {
+                write!(f, "{}: ragged row, expected {} values but found {}", at, expected, found)?;
+                at.quote(f)
+            }
+            ParseError::RowCount { at, expected, found } => {
+                write!(f, "{}: size header declares {} rows but found {}", at, expected, found)?;
+                at.quote(f)
+            }
+            ParseError::TooBig { cells } => {
+                write!(f, "{} cells is too many, boards go up to {}", cells, MAX_CELLS)
+            }
+        }
+    }
+}
+
+impl error::Error for ParseError {}
+
+// Whitespace separated words with the byte offset each starts at
+fn words(line: &str) -> Vec<(usize, &str)> {
+    let mut out = Vec::new();
+    let mut start = None;
+    for (i, c) in line.char_indices() {
+        match (c.is_whitespace(), start) {
+            (false, None) => start = Some(i),
+            (true, Some(s)) => {
+                out.push((s, &line[s..i]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        out.push((s, &line[s..]));
+    }
+    out
+}
+
+// A line of numbers, where each one was found and where the line ends
+struct Row {
+    values: Vec<usize>,
+    spots: Vec<Location>,
+    end: Location,
+}
+
+// Lines are numbered from first, so puzzles in a batch point at the right place
+fn rows(lines: &[&str], first: usize) -> Result<Vec<Row>, ParseError> {
+    let mut out = Vec::new();
+    for (number, line) in lines.iter().enumerate() {
+        let text = line.split('#').next().unwrap();
+        let at = |offset: usize| Location {
+            line: first + number + 1,
+            column: text[..offset].chars().count() + 1,
+            text: line.to_string(),
+        };
+        let mut values = Vec::new();
+        let mut spots = Vec::new();
+        for (offset, word) in words(text) {
+            match word.parse::<usize>() {
+                Ok(value) => values.push(value),
+                Err(_) => {
+                    return Err(ParseError::NotANumber { at: at(offset), token: word.to_owned() })
+                }
+            }
+            spots.push(at(offset));
+        }
+        if !values.is_empty() {
+            out.push(Row { values, spots, end: at(text.trim_end().len()) });
+        }
+    }
+    Ok(out)
+}
+
+/// Rows may be preceded by a line holding just the side length of a square board.
+pub fn parse_input(contents: String) -> Result<Board, ParseError> {
+    let lines: Vec<&str> = contents.lines().collect();
+    parse_lines(&lines, 0)
+}
+
+/// Puzzles separated by blank lines or `---`, each parsed on its own so one bad board
+/// doesn't take the rest down with it.
+pub fn parse_batch(contents: &str) -> Vec<Result<Board, ParseError>> {
+    let lines: Vec<&str> = contents.lines().collect();
+    let mut out = Vec::new();
+    let mut start = 0;
+    for end in 0..=lines.len() {
+        match lines.get(end).map(|line| line.trim()) {
+            Some("") | Some("---") | None => {}
+            Some(_) => continue,
+        }
+        let chunk = &lines[start..end];
+        if chunk.iter().any(|line| !words(line.split('#').next().unwrap()).is_empty()) {
+            out.push(parse_lines(chunk, start));
+        }
+        start = end + 1;
+    }
+    if out.is_empty() {
+        out.push(Err(ParseError::Empty));
+    }
+    out
+}
+
+fn parse_lines(lines: &[&str], first: usize) -> Result<Board, ParseError> {
+    let mut rows = rows(lines, first)?;
+    if rows.is_empty() {
+        return Err(ParseError::Empty);
+    }
+    let header = if rows.len() > 1 && rows[0].values.len() == 1 && rows[1].values.len() > 1 {
+        Some(rows.remove(0))
+    } else {
+        None
+    };
+    let expected = match header {
+        Some(ref header) => header.values[0],
+        None => rows[0].values.len(),
+    };
+    for row in rows.iter() {
+        let found = row.values.len();
+        if found < expected {
+            return Err(ParseError::Missing { at: row.end.clone(), expected, found });
+        }
+        if found > expected {
+            let at = row.spots[expected].clone();
+            return Err(ParseError::Ragged { at, expected, found });
+        }
+    }
+    if let Some(header) = header {
+        let found = rows.len();
+        if found > expected {
+            let at = rows[expected].spots[0].clone();
+            return Err(ParseError::RowCount { at, expected, found });
+        }
+        if found < expected {
+            let at = header.spots[0].clone();
+            return Err(ParseError::RowCount { at, expected, found });
+        }
+    }
+    let cells = rows.len() * expected;
+    if cells > MAX_CELLS {
+        return Err(ParseError::TooBig { cells });
+    }
+    let mut seen: HashMap<usize, &Location> = HashMap::new();
+    for row in rows.iter() {
+        for (&value, at) in row.values.iter().zip(row.spots.iter()) {
+            if value >= cells {
+                return Err(ParseError::OutOfRange { at: at.clone(), value, cells });
+            }
+            if let Some(&first) = seen.get(&value) {
+                let (at, first) = (at.clone(), first.clone());
+                return Err(ParseError::Duplicate { at, value, first });
+            }
+            seen.insert(value, at);
+        }
+    }
+    Ok(rows.into_iter().map(|row| row.values).collect())
+}
+
+/// Square boards get the size header, rectangles are written as bare rows.
+pub fn write_board<W: Write>(out: &mut W, board: &Board) -> io::Result<()> {
+    let (rows, cols) = (board.len(), board[0].len());
+    if rows == cols {
+        writeln!(out, "{}", rows)?;
+    }
+    let width = (rows * cols - 1).to_string().len();
+    for row in board.iter() {
+        let cells: Vec<String> = row.iter().map(|e| format!("{:>w$}", e, w = width)).collect();
+        writeln!(out, "{}", cells.join(" "))?;
+    }
+    Ok(())
+}
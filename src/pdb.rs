@@ -0,0 +1,326 @@
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::io::{self, BufReader, BufWriter, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use crate::heuristic::Heuristic;
+
+const MAGIC: &[u8; 4] = b"NPDB";
+const VERSION: u32 = 2;
+// Occupancy masks are u32s, and a 9 tile group on a 4x4 is already billions of entries
+const MAX_CELLS: usize = 32;
+const MAX_GROUP: usize = 8;
+
+/// Additive disjoint pattern database: each group of tiles gets a table of the fewest
+/// moves of its own tiles needed to reach the goal, so the tables can be summed.
+pub struct Pdb {
+    goal: Vec<Vec<usize>>,
+    groups: Vec<Vec<usize>>,
+    tables: Vec<Vec<u8>>,
+}
+
+impl Pdb {
+    pub fn default_partition(rows: usize, cols: usize) -> Option<&'static str> {
+        match rows * cols - 1 {
+            3 => Some("3"),
+            5 => Some("5"),
+            7 => Some("7"),
+            8 => Some("8"),
+            11 => Some("6-5"),
+            15 => Some("6-6-3"),
+            24 => Some("5-5-5-5-4"),
+            _ => None,
+        }
+    }
+
+    /// Tables only hold for the goal they were built from, so the name carries a hash of it.
+    pub fn file_name(goal: &[Vec<usize>], partition: &str) -> String {
+        let mut hash: u32 = 0x811c_9dc5;
+        for &tile in goal.iter().flat_map(|row| row.iter()) {
+            hash = (hash ^ tile as u32).wrapping_mul(0x0100_0193);
+        }
+        let (rows, cols) = (goal.len(), goal[0].len());
+        format!("pdb-v{}-{}x{}-{}-{:08x}.bin", VERSION, rows, cols, partition, hash)
+    }
+
+    pub fn build(goal: &[Vec<usize>], partition: &str) -> Result<Pdb, &'static str> {
+        let groups = split(goal, partition)?;
+        let tables = groups.iter().map(|tiles| generate(goal, tiles)).collect();
+        Ok(Pdb {
+            goal: goal.to_vec(),
+            groups,
+            tables,
+        })
+    }
+
+    /// Loads the tables cached for this goal and partition, building and caching them if needed.
+    pub fn open(dir: &Path, goal: &[Vec<usize>], partition: &str) -> io::Result<Pdb> {
+        let path = dir.join(Pdb::file_name(goal, partition));
+        match Pdb::load(&path, goal, partition) {
+            Ok(db) => return Ok(db),
+            Err(ref e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        let db =
+            Pdb::build(goal, partition).map_err(|e| io::Error::new(ErrorKind::InvalidInput, e))?;
+        db.save(&path)?;
+        Ok(db)
+    }
+
+    pub fn load(path: &Path, goal: &[Vec<usize>], partition: &str) -> io::Result<Pdb> {
+        let mut bytes = Vec::new();
+        BufReader::new(File::open(path)?).read_to_end(&mut bytes)?;
+        let stale = |what| io::Error::new(ErrorKind::InvalidData, what);
+        if bytes.len() < 4 || &bytes[0..4] != MAGIC {
+            return Err(stale("not a pattern database"));
+        }
+        let mut at = 4;
+        let mut next = || -> io::Result<usize> {
+            if at + 8 > bytes.len() {
+                return Err(stale("truncated pattern database"));
+            }
+            let mut word = [0; 8];
+            word.copy_from_slice(&bytes[at..(at + 8)]);
+            at += 8;
+            Ok(u64::from_le_bytes(word) as usize)
+        };
+        if next()? != VERSION as usize {
+            return Err(stale("pattern database version mismatch"));
+        }
+        let (rows, cols) = (next()?, next()?);
+        let mut stored = Vec::with_capacity(rows);
+        for _ in 0..rows {
+            let mut row = Vec::with_capacity(cols);
+            for _ in 0..cols {
+                row.push(next()?);
+            }
+            stored.push(row);
+        }
+        if stored != goal {
+            return Err(stale("pattern database was built for another goal"));
+        }
+        let mut groups = Vec::new();
+        let mut sizes = Vec::new();
+        for _ in 0..next()? {
+            let mut tiles = Vec::new();
+            for _ in 0..next()? {
+                tiles.push(next()?);
+            }
+            groups.push(tiles);
+            sizes.push(next()?);
+        }
+        if groups != split(goal, partition).map_err(stale)? {
+            return Err(stale("pattern database was built for another partition"));
+        }
+        let mut tables = Vec::with_capacity(sizes.len());
+        for size in sizes {
+            if at + size > bytes.len() {
+                return Err(stale("truncated pattern database"));
+            }
+            tables.push(bytes[at..(at + size)].to_vec());
+            at += size;
+        }
+        Ok(Pdb {
+            goal: stored,
+            groups,
+            tables,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut out = BufWriter::new(File::create(path)?);
+        let word = |out: &mut BufWriter<File>, x: usize| out.write_all(&(x as u64).to_le_bytes());
+        out.write_all(MAGIC)?;
+        word(&mut out, VERSION as usize)?;
+        word(&mut out, self.goal.len())?;
+        word(&mut out, self.goal[0].len())?;
+        for row in self.goal.iter() {
+            for &e in row.iter() {
+                word(&mut out, e)?;
+            }
+        }
+        word(&mut out, self.groups.len())?;
+        for (tiles, table) in self.groups.iter().zip(self.tables.iter()) {
+            word(&mut out, tiles.len())?;
+            for &t in tiles.iter() {
+                word(&mut out, t)?;
+            }
+            word(&mut out, table.len())?;
+        }
+        for table in self.tables.iter() {
+            out.write_all(table)?;
+        }
+        out.flush()
+    }
+}
+
+impl Heuristic for Pdb {
+    fn estimate(&self, board: &[Vec<usize>], _goal: &[Vec<usize>]) -> i64 {
+        let (rows, cols) = (board.len(), board[0].len());
+        let mut cell = vec![0; rows * cols];
+        for i in 0..rows {
+            for j in 0..cols {
+                cell[board[i][j]] = i * cols + j;
+            }
+        }
+        let mut h = 0;
+        for (tiles, table) in self.groups.iter().zip(self.tables.iter()) {
+            let pos: Vec<usize> = tiles.iter().map(|&t| cell[t]).collect();
+            h += table[rank(&pos, rows * cols)] as i64;
+        }
+        h
+    }
+
+    fn admissible(&self) -> bool {
+        true
+    }
+}
+
+pub fn cache_dir(custom: Option<&str>) -> PathBuf {
+    if let Some(dir) = custom {
+        return PathBuf::from(dir);
+    }
+    if let Some(dir) = std::env::var_os("XDG_CACHE_HOME") {
+        return PathBuf::from(dir).join("npuzzle");
+    }
+    if let Some(home) = std::env::var_os("HOME") {
+        return PathBuf::from(home).join(".cache").join("npuzzle");
+    }
+    PathBuf::from(".npuzzle-cache")
+}
+
+// Splits the tiles into groups of the given sizes, taking goal cells in reading order
+fn split(goal: &[Vec<usize>], partition: &str) -> Result<Vec<Vec<usize>>, &'static str> {
+    if goal.len() * goal[0].len() > MAX_CELLS {
+        return Err("pattern databases only go up to 32 cells");
+    }
+    let mut sizes = Vec::new();
+    for part in partition.split('-') {
+        match part.parse::<usize>() {
+            Ok(size) if size > 0 && size <= MAX_GROUP => sizes.push(size),
+            _ => return Err("partition groups must hold between 1 and 8 tiles"),
+        }
+    }
+    let mut tiles = goal.iter().flat_map(|row| row.iter()).filter(|&&t| t != 0);
+    let mut groups = Vec::new();
+    for size in sizes {
+        let group: Vec<usize> = tiles.by_ref().take(size).cloned().collect();
+        if group.len() != size {
+            return Err("partition covers more tiles than the board has");
+        }
+        groups.push(group);
+    }
+    if tiles.next().is_some() {
+        return Err("partition does not cover every tile");
+    }
+    Ok(groups)
+}
+
+// Index of an arrangement of distinct cells among all n!/(n-k)! such arrangements
+fn rank(pos: &[usize], cells: usize) -> usize {
+    let mut r = 0;
+    let mut used = 0u32;
+    for (i, &p) in pos.iter().enumerate() {
+        let before = (used & ((1 << p) - 1)).count_ones() as usize;
+        r = r * (cells - i) + p - before;
+        used |= 1 << p;
+    }
+    r
+}
+
+fn unrank(mut r: usize, k: usize, cells: usize) -> Vec<usize> {
+    let mut digits = vec![0; k];
+    for i in (0..k).rev() {
+        digits[i] = r % (cells - i);
+        r /= cells - i;
+    }
+    let mut pos = Vec::with_capacity(k);
+    let mut used = 0u32;
+    for mut d in digits {
+        let mut c = 0;
+        while used & (1 << c) != 0 || d > 0 {
+            if used & (1 << c) == 0 {
+                d -= 1;
+            }
+            c += 1;
+        }
+        pos.push(c);
+        used |= 1 << c;
+    }
+    pos
+}
+
+// Backward breadth-first search from the goal. Moves of tiles outside the group are free,
+// so each level floods the blank through the empty region before paying for a group move.
+fn generate(goal: &[Vec<usize>], tiles: &[usize]) -> Vec<u8> {
+    let (rows, cols) = (goal.len(), goal[0].len());
+    let cells = rows * cols;
+    let k = tiles.len();
+    let mut adjacent = vec![Vec::new(); cells];
+    for (c, next) in adjacent.iter_mut().enumerate() {
+        let (y, x) = (c / cols, c % cols);
+        if y > 0 {
+            next.push(c - cols);
+        }
+        if y < rows - 1 {
+            next.push(c + cols);
+        }
+        if x > 0 {
+            next.push(c - 1);
+        }
+        if x < cols - 1 {
+            next.push(c + 1);
+        }
+    }
+    let mut home = vec![0; cells];
+    for i in 0..rows {
+        for j in 0..cols {
+            home[goal[i][j]] = i * cols + j;
+        }
+    }
+    let size = ((cells - k + 1)..=cells).product();
+    let mut dist = vec![u8::MAX; size];
+    let mut seen = vec![0u32; size];
+    let start: Vec<usize> = tiles.iter().map(|&t| home[t]).collect();
+    let mut level = vec![(rank(&start, cells), home[0])];
+    let mut depth = 0;
+    while !level.is_empty() {
+        let mut next = Vec::new();
+        for (r, blank) in level {
+            if seen[r] & (1 << blank) != 0 {
+                continue;
+            }
+            if dist[r] == u8::MAX {
+                dist[r] = depth;
+            }
+            let pos = unrank(r, k, cells);
+            let occupied = pos.iter().fold(0u32, |acc, &p| acc | (1 << p));
+            seen[r] |= 1 << blank;
+            let mut region = vec![blank];
+            while let Some(c) = region.pop() {
+                for &t in adjacent[c].iter() {
+                    if occupied & (1 << t) == 0 {
+                        if seen[r] & (1 << t) == 0 {
+                            seen[r] |= 1 << t;
+                            region.push(t);
+                        }
+                        continue;
+                    }
+                    let mut moved = pos.clone();
+                    let which = pos.iter().position(|&p| p == t).unwrap();
+                    moved[which] = c;
+                    let r2 = rank(&moved, cells);
+                    if seen[r2] & (1 << t) == 0 {
+                        next.push((r2, t));
+                    }
+                }
+            }
+        }
+        level = next;
+        depth += 1;
+    }
+    dist
+}
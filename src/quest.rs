@@ -1,94 +1,114 @@
 use std::collections::BinaryHeap;
 use std::collections::HashSet;
+use std::mem;
+use std::time::Instant;
 
-use crate::node::Node;
-use crate::Direction;
-use crate::Heuristic;
+use crate::heuristic::Heuristic;
+use crate::node::{Node, TRAIL_BYTES};
+use crate::state::State;
+use crate::{moves, Limit, Limits};
 
-use crate::insoluble as insolucrate;
-
-pub struct Quest {
+pub struct Quest<H: Heuristic> {
     goal: Vec<Vec<usize>>,
+    target: State,
     open: BinaryHeap<Node>,
-    closed: HashSet<Vec<Vec<usize>>>,
-    heur: Heuristic,
-    greedy: bool,
+    closed: HashSet<State>,
+    heur: H,
+    weight: f64,
     max_space: usize,
+    limits: Limits,
+    started: Instant,
+    stopped: Option<Limit>,
+    // Closest to the goal by the heuristic of the nodes searched so far
+    best: Node,
 }
 
-impl Quest {
+impl<H: Heuristic> Quest<H> {
     pub fn new(
         board: Vec<Vec<usize>>,
-        heur: Heuristic,
-        greedy: bool,
+        heur: H,
+        weight: f64,
         goal: Vec<Vec<usize>>,
-    ) -> Quest {
+        limits: Limits,
+    ) -> Quest<H> {
+        let start = Node::new(board, &heur, weight, &goal);
         let mut open = BinaryHeap::new();
-        open.push(Node::new(board, heur, greedy, &goal));
+        open.push(start.clone());
         Quest {
+            target: State::pack(&goal),
             goal,
             open,
             closed: HashSet::new(),
             heur,
-            greedy,
+            weight,
             max_space: 1,
+            limits,
+            started: Instant::now(),
+            stopped: None,
+            best: start,
         }
     }
 
-    pub fn insoluble(&self) -> bool {
-        let board = self.open.peek().unwrap().board_ref();
-        insolucrate(board, Some(self.get_goal()))
-    }
-
     pub fn step(&mut self) -> Option<Node> {
         if self.open.is_empty() {
             return None;
         }
-        let to_search = self.open.pop().unwrap();
-        if self.closed.contains(to_search.board_ref()) {
+        self.stopped = self.limits.check(self.closed.len(), self.memory(), self.started);
+        if self.stopped.is_some() {
             return None;
         }
-        if to_search.dist() == 0 {
-            return Some(to_search);
-        }
-        let &(y, x) = to_search.path.last().unwrap();
-        if x > 0 {
-            let to_push = to_search.shift(Direction::Left, self.heur, self.greedy, &self.goal);
-            if !self.closed.contains(to_push.board_ref()) {
-                self.open.push(to_push);
-            }
+        let to_search = self.open.pop().unwrap();
+        if self.closed.contains(to_search.state()) {
+            return None;
         }
-        if x < self.goal.len() - 1 {
-            let to_push = to_search.shift(Direction::Right, self.heur, self.greedy, &self.goal);
-            if !self.closed.contains(to_push.board_ref()) {
-                self.open.push(to_push);
-            }
+        if to_search.dist() < self.best.dist() {
+            self.best = to_search.clone();
         }
-        if y > 0 {
-            let to_push = to_search.shift(Direction::Up, self.heur, self.greedy, &self.goal);
-            if !self.closed.contains(to_push.board_ref()) {
-                self.open.push(to_push);
-            }
+        if to_search.reached(&self.target) {
+            return Some(to_search);
         }
-        if y < self.goal.len() - 1 {
-            let to_push = to_search.shift(Direction::Down, self.heur, self.greedy, &self.goal);
-            if !self.closed.contains(to_push.board_ref()) {
+        let (rows, cols) = (self.goal.len(), self.goal[0].len());
+        for (dir, _) in moves(to_search.blank(), rows, cols) {
+            let to_push = to_search.shift(dir, &self.heur, self.weight, &self.goal);
+            if !self.closed.contains(to_push.state()) {
                 self.open.push(to_push);
             }
         }
-        self.closed.insert(to_search.into_board());
+        self.closed.insert(to_search.into_state());
         if self.open.len() > self.max_space {
             self.max_space = self.open.len();
         }
         None
     }
 
-    pub fn get_goal(&self) -> Vec<Vec<usize>> {
-        self.goal.clone()
+    // How many times longer than optimal the solution can be, if that's known at all.
+    // Weighting an admissible heuristic by w can't cost more than a factor of w
+    pub fn bound(&self) -> Option<f64> {
+        if self.heur.admissible() && self.weight.is_finite() {
+            Some(self.weight.max(1.0))
+        } else {
+            None
+        }
     }
 
     pub fn continues(&self) -> bool {
-        !self.open.is_empty()
+        !self.open.is_empty() && self.stopped.is_none()
+    }
+
+    pub fn stopped(&self) -> Option<Limit> {
+        self.stopped
+    }
+
+    pub fn best(&self) -> Node {
+        self.best.clone()
+    }
+
+    // Roughly what the open list and closed set hold, trails included
+    fn memory(&self) -> usize {
+        let (rows, cols) = (self.goal.len(), self.goal[0].len());
+        let cells = State::heap_bytes(rows, cols);
+        let state = mem::size_of::<State>() + cells + TRAIL_BYTES;
+        self.open.len() * (mem::size_of::<Node>() + cells + TRAIL_BYTES) + self.closed.len() * state
     }
 
     pub fn space(&self) -> usize {
@@ -0,0 +1,79 @@
+use std::mem;
+
+use crate::Board;
+
+/// The most cells a board can have, so every tile fits in a u16.
+pub const MAX_CELLS: usize = 1 << 16;
+
+/// A board squeezed down for the open and closed sets, along with its rows and columns.
+#[derive(Clone, Hash, Eq, PartialEq)]
+pub enum State {
+    /// 4 bits per tile, for boards of up to 16 cells.
+    Packed(u8, u8, u64),
+    /// Two bytes per tile for anything bigger, up to MAX_CELLS.
+    Wide(usize, usize, Box<[u16]>),
+}
+
+impl State {
+    pub fn pack(board: &Board) -> State {
+        let (rows, cols) = (board.len(), board[0].len());
+        assert!(rows * cols <= MAX_CELLS, "boards above {} cells are not supported", MAX_CELLS);
+        let tiles = board.iter().flat_map(|row| row.iter());
+        if rows * cols <= 16 {
+            let mut bits = 0;
+            for (i, &tile) in tiles.enumerate() {
+                bits |= (tile as u64) << (4 * i);
+            }
+            State::Packed(rows as u8, cols as u8, bits)
+        } else {
+            State::Wide(rows, cols, tiles.map(|&tile| tile as u16).collect())
+        }
+    }
+
+    /// Bytes a state of this shape keeps on the heap, on top of the State itself.
+    pub fn heap_bytes(rows: usize, cols: usize) -> usize {
+        if rows * cols > 16 {
+            rows * cols * mem::size_of::<u16>()
+        } else {
+            0
+        }
+    }
+
+    pub fn shape(&self) -> (usize, usize) {
+        match self {
+            State::Packed(rows, cols, _) => (*rows as usize, *cols as usize),
+            State::Wide(rows, cols, _) => (*rows, *cols),
+        }
+    }
+
+    pub fn get(&self, (y, x): (usize, usize)) -> usize {
+        let cell = y * self.shape().1 + x;
+        match self {
+            State::Packed(_, _, bits) => ((bits >> (4 * cell)) & 0xf) as usize,
+            State::Wide(_, _, tiles) => tiles[cell] as usize,
+        }
+    }
+
+    fn set(&mut self, (y, x): (usize, usize), tile: usize) {
+        let cell = y * self.shape().1 + x;
+        match self {
+            State::Packed(_, _, bits) => {
+                *bits = (*bits & !(0xf << (4 * cell))) | ((tile as u64) << (4 * cell));
+            }
+            State::Wide(_, _, tiles) => tiles[cell] = tile as u16,
+        }
+    }
+
+    /// Slides the tile at from into the blank at to, returning the tile.
+    pub fn slide(&mut self, from: (usize, usize), to: (usize, usize)) -> usize {
+        let tile = self.get(from);
+        self.set(to, tile);
+        self.set(from, 0);
+        tile
+    }
+
+    pub fn unpack(&self) -> Board {
+        let (rows, cols) = self.shape();
+        (0..rows).map(|i| (0..cols).map(|j| self.get((i, j))).collect()).collect()
+    }
+}
@@ -0,0 +1,67 @@
+use std::error;
+use std::fmt;
+
+use crate::Board;
+
+// Checks a solution from scratch, trusting nothing the search kept track of
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum VerifyError {
+    Empty,
+    /// The first position isn't where the blank starts.
+    Start { expected: (usize, usize), found: (usize, usize) },
+    /// Steps are counted from 1.
+    OffBoard { step: usize, at: (usize, usize) },
+    Jump { step: usize, from: (usize, usize), to: (usize, usize) },
+    WrongEnd { board: Board },
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VerifyError::Empty => write!(f, "the solution has no positions"),
+            VerifyError::Start { expected, found } => {
+                write!(f, "the blank starts at {:?}, not {:?}", expected, found)
+            }
+            VerifyError::OffBoard { step, at } => {
+                write!(f, "step {}: {:?} is off the board", step, at)
+            }
+            VerifyError::Jump { step, from, to } => {
+                write!(f, "step {}: the blank jumps from {:?} to {:?}", step, from, to)
+            }
+            VerifyError::WrongEnd { board } => write!(f, "the solution ends at {:?}", board),
+        }
+    }
+}
+
+impl error::Error for VerifyError {}
+
+/// Replays blank positions, as in Solution::steps, from puzzle and checks they reach goal.
+pub fn verify(puzzle: &Board, goal: &Board, steps: &[(usize, usize)]) -> Result<(), VerifyError> {
+    let mut board = puzzle.clone();
+    let (rows, cols) = (board.len(), board[0].len());
+    let blank = (0..rows * cols)
+        .map(|c| (c / cols, c % cols))
+        .find(|&(i, j)| board[i][j] == 0)
+        .unwrap();
+    let first = *steps.first().ok_or(VerifyError::Empty)?;
+    if first != blank {
+        return Err(VerifyError::Start { expected: blank, found: first });
+    }
+    for (i, w) in steps.windows(2).enumerate() {
+        let (step, from, to) = (i + 1, w[0], w[1]);
+        if to.0 >= rows || to.1 >= cols {
+            return Err(VerifyError::OffBoard { step, at: to });
+        }
+        let apart = (from.0 as i64 - to.0 as i64).abs() + (from.1 as i64 - to.1 as i64).abs();
+        if apart != 1 {
+            return Err(VerifyError::Jump { step, from, to });
+        }
+        board[from.0][from.1] = board[to.0][to.1];
+        board[to.0][to.1] = 0;
+    }
+    if &board != goal {
+        return Err(VerifyError::WrongEnd { board });
+    }
+    Ok(())
+}
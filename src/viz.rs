@@ -21,18 +21,18 @@ impl Viz {
     ) -> Viz {
         use graphics::*;
 
-        let n = goal.len();
-        let width = width as usize;
+        let (rows, cols) = (goal.len(), goal[0].len());
+        let side = width as usize / rows.max(cols);
         let def_t = TextureSettings::new();
-        let mut imgref: Vec<(Image, Texture)> = Vec::with_capacity(n * n);
-        for _ in 0..(n * n) {
-            let piece = Image::new().rect(rectangle::square(0.0, 0.0, (width / n) as f64 - 1.));
+        let mut imgref: Vec<(Image, Texture)> = Vec::with_capacity(rows * cols);
+        for _ in 0..(rows * cols) {
+            let piece = Image::new().rect(rectangle::square(0.0, 0.0, side as f64 - 1.));
             let texture = Texture::from_path(Path::new("assets/argyle.png"), &def_t).unwrap();
             imgref.push((piece, texture));
         }
-        if n < 8 {
-            for i in 0..n {
-                for j in 0..n {
+        if rows < 8 && cols < 8 {
+            for i in 0..rows {
+                for j in 0..cols {
                     let mut p = "assets/split-2018-11-14/".to_owned();
                     p.push_str(&(i.to_string()));
                     p.push_str(&(j.to_string()));
@@ -61,15 +61,15 @@ impl Viz {
 
         const BLACK: [f32; 4] = [0.0; 4];
 
-        let n = self.board.len() as u32;
+        let (rows, cols) = (self.board.len() as u32, self.board[0].len() as u32);
         let def_d = DrawState::default();
 
         self.gl.draw(args.viewport(), |_c, gl| {
             clear(BLACK, gl);
         });
-        for i in 0..n {
-            for j in 0..n {
-                let (x, y) = ((j * args.width / n) as f64, (i * args.height / n) as f64);
+        for i in 0..rows {
+            for j in 0..cols {
+                let (x, y) = ((j * args.width / cols) as f64, (i * args.height / rows) as f64);
                 let val = self.board[i as usize][j as usize];
                 let ref piece = self.imgref[val].0;
                 let ref texture = self.imgref[val].1;
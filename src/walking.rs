@@ -0,0 +1,129 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+
+use crate::heuristic::Heuristic;
+
+/// Limit on both rows and columns; the 5x5 tables are too large to build in memory.
+pub const MAX_LEN: usize = 4;
+
+/// Walking distance: the tiles of each row are only told apart by the row they belong in,
+/// and a precomputed table gives the moves needed to sort those counts with the blank.
+/// Columns get their own table, and the two sums are independent so they can be added.
+pub struct WalkingDistance {
+    home_row: Vec<usize>,
+    home_col: Vec<usize>,
+    rows: HashMap<u64, u8>,
+    cols: HashMap<u64, u8>,
+}
+
+impl WalkingDistance {
+    pub fn new(goal: &[Vec<usize>]) -> WalkingDistance {
+        let (height, width) = (goal.len(), goal[0].len());
+        assert!(height <= MAX_LEN && width <= MAX_LEN, "walking distance only goes up to 4x4");
+        let mut home_row = vec![0; height * width];
+        let mut home_col = vec![0; height * width];
+        for i in 0..height {
+            for j in 0..width {
+                home_row[goal[i][j]] = i;
+                home_col[goal[i][j]] = j;
+            }
+        }
+        let rows = generate(line_key(goal, &home_row, false), height);
+        let cols = generate(line_key(goal, &home_col, true), width);
+        WalkingDistance {
+            home_row,
+            home_col,
+            rows,
+            cols,
+        }
+    }
+}
+
+impl Heuristic for WalkingDistance {
+    fn estimate(&self, board: &[Vec<usize>], _goal: &[Vec<usize>]) -> i64 {
+        let rows = self.rows[&line_key(board, &self.home_row, false)];
+        let cols = self.cols[&line_key(board, &self.home_col, true)];
+        (rows + cols) as i64
+    }
+
+    fn admissible(&self) -> bool {
+        true
+    }
+}
+
+// counts[line][home] is how many tiles in that row (or column) belong in row (or column) home
+fn line_key(board: &[Vec<usize>], home: &[usize], transpose: bool) -> u64 {
+    let lines = if transpose { board[0].len() } else { board.len() };
+    let mut counts = vec![vec![0; lines]; lines];
+    let mut blank = 0;
+    for (i, row) in board.iter().enumerate() {
+        for (j, &tile) in row.iter().enumerate() {
+            let line = if transpose { j } else { i };
+            if tile == 0 {
+                blank = line;
+            } else {
+                counts[line][home[tile]] += 1;
+            }
+        }
+    }
+    encode(&counts, blank)
+}
+
+fn encode(counts: &[Vec<u8>], blank: usize) -> u64 {
+    let mut key = 0;
+    for row in counts.iter() {
+        for &c in row.iter() {
+            key = (key << 3) | c as u64;
+        }
+    }
+    (key << 3) | blank as u64
+}
+
+fn decode(mut key: u64, len: usize) -> (Vec<Vec<u8>>, usize) {
+    let blank = (key & 7) as usize;
+    key >>= 3;
+    let mut counts = vec![vec![0; len]; len];
+    for i in (0..len).rev() {
+        for j in (0..len).rev() {
+            counts[i][j] = (key & 7) as u8;
+            key >>= 3;
+        }
+    }
+    (counts, blank)
+}
+
+// Breadth-first search from the goal, sliding one tile of any home into the blank's line
+fn generate(goal: u64, len: usize) -> HashMap<u64, u8> {
+    let mut dist = HashMap::new();
+    let mut queue = VecDeque::new();
+    dist.insert(goal, 0);
+    queue.push_back(goal);
+    while let Some(key) = queue.pop_front() {
+        let d = dist[&key];
+        let (mut counts, blank) = decode(key, len);
+        let mut neighbours = Vec::with_capacity(2);
+        if blank > 0 {
+            neighbours.push(blank - 1);
+        }
+        if blank < len - 1 {
+            neighbours.push(blank + 1);
+        }
+        for from in neighbours {
+            for home in 0..len {
+                if counts[from][home] == 0 {
+                    continue;
+                }
+                counts[from][home] -= 1;
+                counts[blank][home] += 1;
+                let next = encode(&counts, from);
+                counts[blank][home] -= 1;
+                counts[from][home] += 1;
+                if let Entry::Vacant(entry) = dist.entry(next) {
+                    entry.insert(d + 1);
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+    dist
+}
@@ -0,0 +1,220 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+fn npuzzle(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_npuzzle"))
+        .args(args)
+        .output()
+        .expect("could not run npuzzle")
+}
+
+// A directory of its own under the system temp dir, emptied first
+fn scratch(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("npuzzle-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+// Just enough of a json parser to tell whether a line is one well-formed value
+struct Json<'a> {
+    text: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Json<'a> {
+    fn check(line: &str) -> Result<(), String> {
+        let mut json = Json { text: line.as_bytes(), at: 0 };
+        json.value()?;
+        json.space();
+        if json.at != json.text.len() {
+            return Err(format!("trailing input at {}", json.at));
+        }
+        Ok(())
+    }
+
+    fn space(&mut self) {
+        while self.peek().is_some_and(|c| c.is_ascii_whitespace()) {
+            self.at += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.get(self.at).cloned()
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), String> {
+        self.space();
+        if self.peek() != Some(c) {
+            return Err(format!("expected `{}` at {}", c as char, self.at));
+        }
+        self.at += 1;
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<(), String> {
+        self.space();
+        match self.peek() {
+            Some(b'{') => self.list(b'{', b'}', true),
+            Some(b'[') => self.list(b'[', b']', false),
+            Some(b'"') => self.string(),
+            Some(b't') => self.word("true"),
+            Some(b'f') => self.word("false"),
+            Some(b'n') => self.word("null"),
+            Some(c) if c == b'-' || c.is_ascii_digit() => self.number(),
+            _ => Err(format!("expected a value at {}", self.at)),
+        }
+    }
+
+    fn list(&mut self, open: u8, close: u8, keyed: bool) -> Result<(), String> {
+        self.expect(open)?;
+        self.space();
+        if self.peek() == Some(close) {
+            self.at += 1;
+            return Ok(());
+        }
+        loop {
+            if keyed {
+                self.space();
+                self.string()?;
+                self.expect(b':')?;
+            }
+            self.value()?;
+            self.space();
+            match self.peek() {
+                Some(b',') => self.at += 1,
+                _ => return self.expect(close),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<(), String> {
+        self.expect(b'"')?;
+        loop {
+            match self.peek() {
+                Some(b'"') => {
+                    self.at += 1;
+                    return Ok(());
+                }
+                Some(b'\\') => self.at += 2,
+                Some(c) if c >= 0x20 => self.at += 1,
+                _ => return Err(format!("unterminated string at {}", self.at)),
+            }
+        }
+    }
+
+    fn word(&mut self, word: &str) -> Result<(), String> {
+        if !self.text[self.at..].starts_with(word.as_bytes()) {
+            return Err(format!("expected {} at {}", word, self.at));
+        }
+        self.at += word.len();
+        Ok(())
+    }
+
+    fn number(&mut self) -> Result<(), String> {
+        let start = self.at;
+        if self.peek() == Some(b'-') {
+            self.at += 1;
+        }
+        let digits = |json: &mut Json| {
+            let from = json.at;
+            while json.peek().is_some_and(|c| c.is_ascii_digit()) {
+                json.at += 1;
+            }
+            json.at > from
+        };
+        let mut ok = digits(self);
+        if self.peek() == Some(b'.') {
+            self.at += 1;
+            ok &= digits(self);
+        }
+        if let Some(b'e') | Some(b'E') = self.peek() {
+            self.at += 1;
+            if let Some(b'+') | Some(b'-') = self.peek() {
+                self.at += 1;
+            }
+            ok &= digits(self);
+        }
+        if !ok {
+            return Err(format!("bad number at {}", start));
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn json_checker() {
+    assert!(Json::check(r#"{"a":[1,-2.5,3e4],"b":"\"x\"","c":null,"d":true}"#).is_ok());
+    assert!(Json::check(r#"{"weight":inf}"#).is_err());
+    assert!(Json::check("Generating pattern database /tmp/x...").is_err());
+    assert!(Json::check(r#"{"a":1}{"#).is_err());
+}
+
+#[test]
+fn pdb_json_output() {
+    let dir = scratch("pdb-json");
+    let cache = dir.to_str().unwrap();
+    let args = ["--auto", "3", "--seed", "7", "-q", "-h", "pdb", "--format", "json"];
+    let output = npuzzle(&[&args[..], &["--cache-dir", cache]].concat());
+    let _ = fs::remove_dir_all(&dir);
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Generating pattern database"));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), 1);
+    for line in stdout.lines() {
+        if let Err(e) = Json::check(line) {
+            panic!("{}: {}", e, line);
+        }
+    }
+}
+
+#[test]
+fn weights() {
+    let auto = ["--auto", "3", "--seed", "7", "-q"];
+    for weight in &["inf", "1e309", "NaN", "0.5", "0", "-2"] {
+        let output = npuzzle(&[&auto[..], &["--weight", weight]].concat());
+        assert_eq!(Some(1), output.status.code(), "--weight {}", weight);
+    }
+    for extra in &[&["--weight", "1.5"][..], &["--greedy"][..]] {
+        let output = npuzzle(&[&auto[..], &["--format", "json"], extra].concat());
+        assert!(output.status.success());
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(Json::check(stdout.trim_end()).is_ok(), "{}", stdout);
+    }
+}
+
+#[test]
+fn bidirectional_pdb_is_not_cached() {
+    let dir = scratch("bidir-pdb");
+    let cache = dir.to_str().unwrap();
+    let args = ["--auto", "3", "--seed", "7", "-q", "-h", "pdb", "--algorithm", "bidir"];
+    let output = npuzzle(&[&args[..], &["--cache-dir", cache]].concat());
+    assert!(output.status.success());
+    // Only the forward database, aimed at the goal, ends up in the cache
+    let cached = fs::read_dir(&dir).unwrap().count();
+    let _ = fs::remove_dir_all(&dir);
+    assert_eq!(1, cached);
+}
+
+#[test]
+fn replay_exit_codes() {
+    // The board is the goal, with the blank in the middle
+    let board = "puzzles/parsing/clean.txt";
+    for &(moves, code) in &[("UD", 0), ("DDLU", 5), ("UUU", 5), ("UX", 1)] {
+        let output = npuzzle(&["replay", board, moves]);
+        assert_eq!(Some(code), output.status.code(), "replaying {}", moves);
+    }
+}
+
+#[test]
+fn max_memory_values() {
+    let auto = ["--auto", "3", "--seed", "7", "-q"];
+    for memory in &["20000000000000G", "18446744073709551616", "12X", "G"] {
+        let output = npuzzle(&[&auto[..], &["--max-memory", memory]].concat());
+        assert_eq!(Some(1), output.status.code(), "--max-memory {}", memory);
+    }
+    let output = npuzzle(&[&auto[..], &["--max-memory", "1G"]].concat());
+    assert!(output.status.success());
+}